@@ -0,0 +1,227 @@
+use age_core::format::{FileKey, Stanza};
+use age_core::secrecy::ExposeSecret;
+use age_plugin::{identity, recipient, run_state_machine, Callbacks};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::PathBuf;
+
+use age_threshold::types::{
+    AgeIdentity, AgeRecipient, ThresholdIdentity, ThresholdRecipient, PLUGIN_NAME,
+};
+
+use clap::*;
+
+/// Stanza tag carrying a whole threshold header in its body.
+const STANZA_TAG: &str = "threshold";
+
+#[derive(Default)]
+struct RecipientPlugin {
+    recipients: Vec<ThresholdRecipient>,
+}
+
+impl recipient::RecipientPluginV1 for RecipientPlugin {
+    fn add_recipient(
+        &mut self,
+        index: usize,
+        plugin_name: &str,
+        bytes: &[u8],
+    ) -> Result<(), recipient::Error> {
+        if plugin_name != PLUGIN_NAME {
+            return Err(recipient::Error::Recipient {
+                index,
+                message: "unsupported plugin".to_string(),
+            });
+        }
+        let r =
+            ThresholdRecipient::from_bytes(bytes).map_err(|err| recipient::Error::Recipient {
+                index,
+                message: err.to_string(),
+            })?;
+        self.recipients.push(r);
+        Ok(())
+    }
+
+    fn add_identity(
+        &mut self,
+        index: usize,
+        _plugin_name: &str,
+        _bytes: &[u8],
+    ) -> Result<(), recipient::Error> {
+        Err(recipient::Error::Identity {
+            index,
+            message: "encrypting to threshold identities is not supported".to_string(),
+        })
+    }
+
+    fn wrap_file_keys(
+        &mut self,
+        file_keys: Vec<FileKey>,
+        _callbacks: impl Callbacks<recipient::Error>,
+    ) -> io::Result<Result<Vec<Vec<Stanza>>, Vec<recipient::Error>>> {
+        let mut files = vec![];
+        let mut errors = vec![];
+        for file_key in &file_keys {
+            let mut stanzas = vec![];
+            for (index, r) in self.recipients.iter().enumerate() {
+                match age_threshold::wrap_file_key(file_key, &r.recipients, r.threshold) {
+                    Ok(header) => stanzas.push(Stanza {
+                        tag: STANZA_TAG.to_string(),
+                        args: vec![],
+                        body: header,
+                    }),
+                    Err(err) => errors.push(recipient::Error::Recipient {
+                        index,
+                        message: err.to_string(),
+                    }),
+                }
+            }
+            files.push(stanzas);
+        }
+        Ok(if errors.is_empty() {
+            Ok(files)
+        } else {
+            Err(errors)
+        })
+    }
+}
+
+#[derive(Default)]
+struct IdentityPlugin {
+    identities: Vec<AgeIdentity>,
+}
+
+impl identity::IdentityPluginV1 for IdentityPlugin {
+    fn add_identity(
+        &mut self,
+        index: usize,
+        plugin_name: &str,
+        bytes: &[u8],
+    ) -> Result<(), identity::Error> {
+        if plugin_name != PLUGIN_NAME {
+            return Err(identity::Error::Identity {
+                index,
+                message: "unsupported plugin".to_string(),
+            });
+        }
+        let i = ThresholdIdentity::from_bytes(bytes).map_err(|err| identity::Error::Identity {
+            index,
+            message: err.to_string(),
+        })?;
+        self.identities.extend(i.identities);
+        Ok(())
+    }
+
+    fn unwrap_file_keys(
+        &mut self,
+        files: Vec<Vec<Stanza>>,
+        _callbacks: impl Callbacks<identity::Error>,
+    ) -> io::Result<HashMap<usize, Result<FileKey, Vec<identity::Error>>>> {
+        let mut file_keys = HashMap::new();
+        for (file_index, stanzas) in files.iter().enumerate() {
+            let mut errors = vec![];
+            for (stanza_index, stanza) in stanzas.iter().enumerate() {
+                if stanza.tag != STANZA_TAG {
+                    continue;
+                }
+                match age_threshold::unwrap_file_key(&self.identities, &stanza.body) {
+                    Ok(Some(file_key)) => {
+                        file_keys.insert(file_index, Ok(file_key));
+                        break;
+                    }
+                    Ok(None) => continue,
+                    Err(err) => errors.push(identity::Error::Stanza {
+                        file_index,
+                        stanza_index,
+                        message: err.to_string(),
+                    }),
+                }
+            }
+            if !errors.is_empty() && !file_keys.contains_key(&file_index) {
+                file_keys.insert(file_index, Err(errors));
+            }
+        }
+        Ok(file_keys)
+    }
+}
+
+fn main() -> io::Result<()> {
+    let cmd = command!()
+        .arg(arg!(--"age-plugin" [STATE_MACHINE] "Run the given age plugin state machine."))
+        .args_conflicts_with_subcommands(true)
+        .subcommand(Command::new("recipient").about("Print a recipient for a threshold of the given members.").args(&[
+                             arg!(-t --threshold <THRESHOLD> "Threshold number of members needed to decrypt.").value_parser(value_parser!(u32)),
+                             arg!(-r --recipient [RECIPIENT] ... "Member recipient. Can be repeated."),
+                             arg!(-R --"recipients-file" [PATH] ... "Member recipients listed at PATH. Can be repeated.").value_parser(value_parser!(PathBuf)),
+        ]))
+        .subcommand(Command::new("identity").about("Print an identity wrapping the given member identities.").args(&[
+                             arg!(<PATH> ... "Member identity files.").value_parser(value_parser!(PathBuf)),
+        ]));
+    let m = cmd.get_matches();
+
+    if let Some(state_machine) = m.get_one::<String>("age-plugin") {
+        return run_state_machine(
+            state_machine,
+            Some(RecipientPlugin::default),
+            Some(IdentityPlugin::default),
+        );
+    }
+    match m.subcommand() {
+        Some(("recipient", m)) => {
+            let mut recipients = vec![];
+            for r in m.get_many::<String>("recipient").into_iter().flatten() {
+                recipients.push(AgeRecipient::from_bech32(r).map_err(io::Error::other)?);
+            }
+            for f in m
+                .get_many::<PathBuf>("recipients-file")
+                .into_iter()
+                .flatten()
+            {
+                for l in read_text_file(f)? {
+                    recipients.push(AgeRecipient::from_bech32(&l).map_err(io::Error::other)?);
+                }
+            }
+            let threshold = *m.get_one::<u32>("threshold").unwrap();
+            if threshold == 0 || threshold as usize > recipients.len() {
+                return Err(io::Error::other("invalid threshold"));
+            }
+            let r = ThresholdRecipient {
+                threshold,
+                recipients,
+            };
+            println!("{}", r.to_bech32());
+        }
+        Some(("identity", m)) => {
+            let mut identities = vec![];
+            for f in m.get_many::<PathBuf>("PATH").into_iter().flatten() {
+                let lines = read_text_file(f)?;
+                let first = lines
+                    .first()
+                    .ok_or(io::Error::other("empty identity file"))?;
+                identities.push(AgeIdentity::from_bech32(first).map_err(io::Error::other)?);
+            }
+            let i = ThresholdIdentity { identities };
+            println!("{}", i.to_bech32().expose_secret());
+        }
+        _ => {
+            return Err(io::Error::other(
+                "expected --age-plugin or a subcommand, see --help",
+            ))
+        }
+    }
+    Ok(())
+}
+
+fn read_text_file(path: &PathBuf) -> io::Result<Vec<String>> {
+    let file = File::open(path)?;
+    let mut v = vec![];
+    for l in io::BufReader::new(file).lines() {
+        let line = l?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            v.push(line.to_string());
+        }
+    }
+    Ok(v)
+}
@@ -0,0 +1,67 @@
+use age::cli_common::UiCallbacks;
+use age::secrecy::ExposeSecret;
+use age_threshold::types::{AgeIdentity, AgeRecipient, ThresholdIdentity, ThresholdRecipient};
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+
+/// Let age find the plugin binary built along with this test.
+fn add_plugin_to_path() {
+    let bin = std::path::Path::new(env!("CARGO_BIN_EXE_age-plugin-threshold"));
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let paths =
+        std::iter::once(bin.parent().unwrap().to_path_buf()).chain(std::env::split_paths(&path));
+    std::env::set_var("PATH", std::env::join_paths(paths).unwrap());
+}
+
+/// Decrypt `enc` as age does, through the plugin with the given member identities.
+fn age_decrypt(members: Vec<AgeIdentity>, enc: &[u8]) -> Result<Vec<u8>, age::DecryptError> {
+    let encoded = ThresholdIdentity {
+        identities: members,
+    }
+    .to_bech32();
+    let identity = age::plugin::Identity::from_str(encoded.expose_secret()).unwrap();
+    let plugin = age::plugin::IdentityPluginV1::new("threshold", &[identity], UiCallbacks)?;
+    let age::Decryptor::Recipients(decryptor) = age::Decryptor::new(enc)? else {
+        unreachable!()
+    };
+    let mut reader = decryptor.decrypt(std::iter::once(&plugin as &dyn age::Identity))?;
+    let mut buf = vec![];
+    reader.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+#[test]
+fn age_through_plugin() -> io::Result<()> {
+    add_plugin_to_path();
+    let msg = b"encrypted by a stock age client";
+    let members: Vec<_> = (0..3).map(|_| age::x25519::Identity::generate()).collect();
+    let recipient = ThresholdRecipient {
+        threshold: 2,
+        recipients: members
+            .iter()
+            .map(|m| AgeRecipient::X25519(m.to_public()))
+            .collect(),
+    }
+    .to_bech32();
+
+    let recipient = age::plugin::Recipient::from_str(&recipient).unwrap();
+    let plugin = age::plugin::RecipientPluginV1::new("threshold", &[recipient], &[], UiCallbacks)
+        .map_err(io::Error::other)?;
+    let encryptor = age::Encryptor::with_recipients(vec![Box::new(plugin)]).unwrap();
+    let mut enc = vec![];
+    let mut writer = encryptor.wrap_output(&mut enc).map_err(io::Error::other)?;
+    writer.write_all(msg)?;
+    writer.finish()?;
+
+    let ids = |i: &[usize]| -> Vec<_> {
+        i.iter()
+            .map(|&i| AgeIdentity::X25519(members[i].clone()))
+            .collect()
+    };
+    for quorum in [[0, 1], [1, 2], [0, 2]] {
+        let buf = age_decrypt(ids(&quorum), &enc).map_err(io::Error::other)?;
+        assert_eq!(&buf[..], &msg[..]);
+    }
+    assert!(age_decrypt(ids(&[2]), &enc).is_err());
+    Ok(())
+}
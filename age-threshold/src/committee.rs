@@ -0,0 +1,252 @@
+//! Files encrypted to a committee key, and the ceremony that generates that key.
+
+use age_core::secrecy::Zeroize;
+use curve25519_dalek::scalar::Scalar;
+use nom_bufreader::bufreader::BufReader;
+use std::io;
+use std::io::prelude::*;
+
+use crate::header::{read_committee_header, read_header, write_header};
+use crate::payload::{decrypt_stream, encrypt_payload};
+use crate::shares::{encrypt_shares, unlock_shares};
+use crate::{crypto, format, types};
+
+const DEAL_VERSION_LINE: &[u8] = b"age-threshold/committee/deal/v1\n";
+
+/// Deal member `index`'s contribution to the key of a committee of `members`, in index order,
+/// any `t` of which will be able to decrypt. Writes the deal, to be sent to every member.
+///
+/// The returned dealing must be kept by the member for [`committee_reveal`].
+pub fn committee_deal(
+    index: u32,
+    members: &[types::AgeRecipient],
+    t: u32,
+    output: &mut impl Write,
+) -> io::Result<types::CommitteeDealing> {
+    let n = members.len() as u32;
+    if t == 0 || t > n {
+        return Err(io::Error::other("invalid threshold"));
+    }
+    if index == 0 || index > n {
+        return Err(io::Error::other("invalid member index"));
+    }
+    let (coeffs, shares, commitments) = crypto::committee::deal(t, n);
+    let mut header = types::Header {
+        version: types::HeaderVersion::default(),
+        threshold: t as usize,
+        commitments: types::Commitments::Pedersen(commitments),
+        extensions: vec![],
+        enc_shares: vec![],
+    };
+    header.enc_shares = encrypt_shares(&shares, members, &header)?;
+    output.write_all(DEAL_VERSION_LINE)?;
+    writeln!(output, "{}", index)?;
+    write_header(output, &header)?;
+    Ok(types::CommitteeDealing::new(index, coeffs))
+}
+
+/// Parse a deal written by [`committee_deal`].
+pub fn read_deal(input: &mut impl Read) -> io::Result<types::Deal> {
+    let mut input = BufReader::new(input);
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    if line.as_bytes() != DEAL_VERSION_LINE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a deal"));
+    }
+    line.clear();
+    input.read_line(&mut line)?;
+    let dealer = line.trim().parse().map_err(io::Error::other)?;
+    let header = read_header(&mut input)?;
+    Ok(types::Deal { dealer, header })
+}
+
+/// Check one's shares of all `deals`, then reveal the commitments of one's own dealing.
+///
+/// Every member must be given the same deals: a deal that fails for anyone
+/// must be left out by everyone, which excludes its dealer.
+pub fn committee_reveal(
+    identities: &[types::AgeIdentity],
+    dealing: &types::CommitteeDealing,
+    deals: &[types::Deal],
+) -> io::Result<types::CommitteeReveal> {
+    let shares = committee_shares(identities, deals)?;
+    if shares[0].index != dealing.dealer {
+        return Err(io::Error::other("dealing belongs to another member"));
+    }
+    if dealing.coeffs().len() != deals[0].header.threshold {
+        return Err(io::Error::other("dealing for another threshold"));
+    }
+    Ok(types::CommitteeReveal {
+        dealer: dealing.dealer,
+        commitments: crypto::committee::reveal(dealing.coeffs()),
+    })
+}
+
+/// Finish key generation: check one's shares of `deals` against what their dealers revealed,
+/// and add them up into one's key share.
+///
+/// Every member given the same deals and reveals ends up with the same committee recipient.
+pub fn committee_finish(
+    identities: &[types::AgeIdentity],
+    deals: &[types::Deal],
+    reveals: &[types::CommitteeReveal],
+) -> io::Result<types::CommitteeIdentity> {
+    let shares = committee_shares(identities, deals)?;
+    if let Some(r) = reveals
+        .iter()
+        .find(|r| !deals.iter().any(|d| d.dealer == r.dealer))
+    {
+        return Err(io::Error::other(format!(
+            "reveal from member {} without a deal",
+            r.dealer
+        )));
+    }
+
+    let mut secret = Scalar::ZERO;
+    let mut commitments = vec![];
+    for (deal, share) in deals.iter().zip(&shares) {
+        let reveal = reveals
+            .iter()
+            .find(|r| r.dealer == deal.dealer)
+            .ok_or_else(|| {
+                io::Error::other(format!("missing reveal from member {}", deal.dealer))
+            })?;
+        if reveal.commitments.len() != deal.header.threshold
+            || !crypto::committee::verify_reveal(share, reveal)
+        {
+            return Err(io::Error::other(format!(
+                "inconsistent reveal from member {}",
+                deal.dealer
+            )));
+        }
+        secret += share.s;
+        commitments = if commitments.is_empty() {
+            reveal.commitments.clone()
+        } else {
+            crypto::add_commitments(&commitments, &reveal.commitments)
+        };
+    }
+    let recipient = types::CommitteeRecipient { commitments };
+    let identity = types::CommitteeIdentity::new(shares[0].index, secret, recipient);
+    secret.zeroize();
+    identity.map_err(io::Error::other)
+}
+
+/// Encrypt `input` to a committee. Any threshold of its members can decrypt it together,
+/// see [`partial_decrypt`] and [`decrypt_committee`].
+pub fn encrypt_committee(
+    recipient: &types::CommitteeRecipient,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let (ephemeral, shared) = crypto::committee::encapsulate(recipient);
+    let header = types::CommitteeHeader {
+        threshold: recipient.threshold(),
+        commitments: recipient.commitments.clone(),
+        ephemeral,
+    };
+    let (output, _) = cookie_factory::gen(format::write::committee_header(&header), output)
+        .map_err(io::Error::other)?;
+    encrypt_payload(
+        &crypto::committee::file_key(&ephemeral, &shared),
+        input,
+        output,
+    )
+}
+
+/// Compute one's partial decryption of a file made by [`encrypt_committee`].
+///
+/// The header is passed to `approve` first, since a partial decryption releases as much
+/// as a share.
+pub fn partial_decrypt(
+    identity: &types::CommitteeIdentity,
+    approve: impl FnOnce(&types::CommitteeHeader) -> io::Result<()>,
+    input: &mut impl Read,
+) -> io::Result<types::PartialDecryption> {
+    let header = read_committee_header(&mut BufReader::new(input))?;
+    if header.recipient() != identity.recipient {
+        return Err(io::Error::other("file is encrypted to another committee"));
+    }
+    approve(&header)?;
+    Ok(crypto::committee::partial_decrypt(
+        identity,
+        &header.ephemeral,
+    ))
+}
+
+/// Decrypt a file made by [`encrypt_committee`] with the partial decryptions of enough members.
+/// All partial decryptions are verified first.
+pub fn decrypt_committee(
+    partials: &[types::PartialDecryption],
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut input = BufReader::new(input);
+    let header = read_committee_header(&mut input)?;
+    let recipient = header.recipient();
+
+    let mut verified: Vec<types::PartialDecryption> = vec![];
+    for p in partials {
+        if !crypto::committee::verify_partial(&recipient, &header.ephemeral, p) {
+            return Err(io::Error::other(format!(
+                "invalid partial decryption from member {}",
+                p.index
+            )));
+        }
+        if !verified.iter().any(|v| v.index == p.index) {
+            verified.push(p.clone());
+        }
+    }
+    if verified.len() < header.threshold {
+        return Err(io::Error::other("not enough partial decryptions"));
+    }
+    let shared = crypto::committee::combine(&verified[..header.threshold]);
+    decrypt_stream(
+        &crypto::committee::file_key(&header.ephemeral, &shared),
+        input,
+        output,
+    )
+}
+
+/// Unwrap and verify one's share of every deal, which must all be for the same member
+/// and threshold.
+fn committee_shares(
+    identities: &[types::AgeIdentity],
+    deals: &[types::Deal],
+) -> io::Result<Vec<types::SecretShare>> {
+    let Some(first) = deals.first() else {
+        return Err(io::Error::other("no deals"));
+    };
+    let mut shares: Vec<types::SecretShare> = vec![];
+    for (i, deal) in deals.iter().enumerate() {
+        if deals[..i].iter().any(|d| d.dealer == deal.dealer) {
+            return Err(io::Error::other(format!(
+                "duplicate deal from member {}",
+                deal.dealer
+            )));
+        }
+        let header = &deal.header;
+        let commitments = header.commitments.pedersen().map_err(io::Error::other)?;
+        if header.threshold != first.header.threshold || commitments.len() != header.threshold {
+            return Err(io::Error::other("deals for different thresholds"));
+        }
+        let mut unlocked = unlock_shares(identities, header).map_err(|err| {
+            io::Error::other(format!("deal from member {}: {}", deal.dealer, err))
+        })?;
+        let share = match unlocked.len() {
+            0 => {
+                return Err(io::Error::other(format!(
+                    "no share in the deal from member {}",
+                    deal.dealer
+                )))
+            }
+            1 => unlocked.remove(0),
+            _ => return Err(io::Error::other("more than one share unwrapped")),
+        };
+        if shares.first().is_some_and(|s| s.index != share.index) {
+            return Err(io::Error::other("deals unwrapped for different members"));
+        }
+        shares.push(share);
+    }
+    Ok(shares)
+}
@@ -0,0 +1,138 @@
+//! Conversion between threshold files and plain age files.
+
+use age::cli_common::UiCallbacks;
+use age_core::format::{FileKey, Stanza};
+use age_core::primitives::hkdf;
+use age_core::secrecy::ExposeSecret;
+use hmac::{Hmac, Mac};
+use nom_bufreader::bufreader::BufReader;
+use sha2::Sha256;
+use std::io;
+use std::io::prelude::*;
+
+use crate::header::{new_header, read_age_header, read_header, write_header};
+use crate::payload::{little_endian, reencrypt_payload};
+use crate::shares::{reconstruct_file_key, unlock_shares, wrap_share_key};
+use crate::{format, types};
+
+const HEADER_KEY_LABEL: &[u8] = b"header";
+
+/// Share an existing file key among `recipients`, returning the serialized threshold header.
+///
+/// This lets other formats embed a threshold header, as the age plugin does.
+pub fn wrap_file_key(
+    file_key: &FileKey,
+    recipients: &[types::AgeRecipient],
+    t: u32,
+) -> io::Result<Vec<u8>> {
+    write_header(
+        vec![],
+        &new_header(file_key, recipients, t, &Default::default())?,
+    )
+}
+
+/// Reconstruct the file key from a serialized threshold header, as produced by [`wrap_file_key`].
+///
+/// Returns `None` if `identities` do not unlock enough shares.
+pub fn unwrap_file_key(
+    identities: &[types::AgeIdentity],
+    header: &[u8],
+) -> io::Result<Option<FileKey>> {
+    let header = read_header(&mut BufReader::new(header))?;
+
+    let shares = unlock_shares(identities, &header)?;
+    if shares.len() < header.threshold {
+        return Ok(None);
+    }
+    reconstruct_file_key(&header, &shares).map(Some)
+}
+
+/// Turn a plain age v1 file into a threshold file, copying the payload as is.
+///
+/// The file key is unwrapped with `identities` and shared among `recipients`.
+pub fn from_age(
+    identities: &[types::AgeIdentity],
+    recipients: &[types::AgeRecipient],
+    t: u32,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut input = BufReader::new(input);
+    let age_header = read_age_header(&mut input)?;
+
+    let mut file_key = None;
+    for identity in identities {
+        let identity = identity
+            .to_identity(UiCallbacks)
+            .map_err(io::Error::other)?;
+        match identity.unwrap_stanzas(&age_header.stanzas) {
+            Some(Ok(fk)) => {
+                file_key = Some(fk);
+                break;
+            }
+            Some(Err(err)) => return Err(io::Error::other(err)),
+            None => continue,
+        }
+    }
+    let file_key = file_key.ok_or(io::Error::other("no identity matched"))?;
+    if age_header_mac(&file_key, &age_header.stanzas)? != age_header.mac {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "bad header mac"));
+    }
+
+    let output = write_header(
+        output,
+        &new_header(&file_key, recipients, t, &Default::default())?,
+    )?;
+    io::copy(&mut input, output)?;
+    Ok(())
+}
+
+/// Turn a threshold file into a plain age v1 file for `recipients`.
+///
+/// The file key is reconstructed from the shares that `identities` can unlock. The payload is
+/// copied as is, unless a version 0 header counts its chunks little-endian: it is then
+/// re-encrypted under a fresh nonce, since age counts them big-endian.
+pub fn to_age(
+    identities: &[types::AgeIdentity],
+    recipients: &[types::AgeRecipient],
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    if recipients.is_empty() {
+        return Err(io::Error::other("no recipients"));
+    }
+    let mut input = BufReader::new(input);
+    let header = read_header(&mut input)?;
+    if header.padded() {
+        return Err(io::Error::other("padded files cannot be converted to age"));
+    }
+
+    let shares = unlock_shares(identities, &header)?;
+    let file_key = reconstruct_file_key(&header, &shares)?;
+
+    let stanzas = wrap_share_key(&file_key, recipients)?;
+    let mac = age_header_mac(&file_key, &stanzas)?;
+    let (output, _) = cookie_factory::gen(
+        format::write::age_header(&types::AgeHeader { stanzas, mac }),
+        output,
+    )
+    .map_err(io::Error::other)?;
+    if little_endian(header.version) {
+        return reencrypt_payload(&file_key, true, input, output);
+    }
+    io::copy(&mut input, output)?;
+    Ok(())
+}
+
+fn age_header_mac(file_key: &FileKey, stanzas: &[Stanza]) -> io::Result<[u8; 32]> {
+    let (mac_input, _) = cookie_factory::gen(format::write::age_header_mac_input(stanzas), vec![])
+        .map_err(io::Error::other)?;
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&hkdf(
+        &[],
+        HEADER_KEY_LABEL,
+        file_key.expose_secret(),
+    ))
+    .expect("any key length is valid");
+    mac.update(&mac_input);
+    Ok(mac.finalize().into_bytes().into())
+}
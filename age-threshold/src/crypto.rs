@@ -1,10 +1,15 @@
+pub mod committee;
+pub mod dleq;
+pub mod hash_vss;
+pub mod pvss;
+
 use age_core::format::{FileKey, FILE_KEY_BYTES};
 use age_core::secrecy::{ExposeSecret, Zeroize};
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::scalar::Scalar;
 use once_cell::sync::Lazy;
 use rand::rngs::OsRng;
-use sha2::Sha512;
+use sha2::{Digest, Sha256, Sha512};
 
 use crate::types::SecretShare;
 
@@ -34,6 +39,22 @@ fn decode(s: &Scalar) -> FileKey {
     FileKey::from(buf)
 }
 
+/// Scalars hold 31 bytes of an arbitrary secret without reduction.
+const CHUNK_BYTES: usize = 31;
+
+fn encode_bytes(secret: &[u8]) -> Vec<Scalar> {
+    secret
+        .chunks(CHUNK_BYTES)
+        .map(|chunk| {
+            let mut buf = [0u8; 32];
+            buf[..chunk.len()].copy_from_slice(chunk);
+            let s = Scalar::from_bytes_mod_order(buf);
+            buf.zeroize();
+            s
+        })
+        .collect()
+}
+
 fn poly_eval(coeffs: &[Scalar], x: Scalar) -> Scalar {
     let mut r = Scalar::ZERO;
     let mut acc = Scalar::ONE;
@@ -44,8 +65,69 @@ fn poly_eval(coeffs: &[Scalar], x: Scalar) -> Scalar {
     r
 }
 
+/// Evaluate a polynomial given by commitments to its coefficients, `Σ C_i · xⁱ`.
+fn eval_commitments(commitments: &[RistrettoPoint], x: Scalar) -> RistrettoPoint {
+    let mut r = RistrettoPoint::default();
+    let mut acc = Scalar::ONE;
+    for c in commitments {
+        r += c * acc;
+        acc *= x;
+    }
+    r
+}
+
 pub fn share_secret(fk: &FileKey, k: u32, n: u32) -> (Vec<SecretShare>, Vec<RistrettoPoint>) {
-    let s0 = encode(fk);
+    let indices: Vec<_> = (1..=n).collect();
+    share_scalar(encode(fk), k, &indices)
+}
+
+/// Share a secret of any length, as one sharing per 31-byte chunk.
+///
+/// Returns the chunk shares of each holder, in holder order, and the commitments of each chunk.
+pub fn share_bytes(
+    secret: &[u8],
+    k: u32,
+    n: u32,
+) -> (Vec<Vec<SecretShare>>, Vec<Vec<RistrettoPoint>>) {
+    let indices: Vec<_> = (1..=n).collect();
+    let mut holders = vec![vec![]; n as usize];
+    let mut commitments = vec![];
+    for s0 in encode_bytes(secret) {
+        let (shares, c) = share_scalar(s0, k, &indices);
+        for (holder, share) in holders.iter_mut().zip(shares) {
+            holder.push(share);
+        }
+        commitments.push(c);
+    }
+    (holders, commitments)
+}
+
+/// Share zero among the given indices, for proactive refresh.
+///
+/// Adding the resulting shares to existing ones and the commitments to the existing
+/// commitments yields a fresh sharing of the same secret: old shares become useless
+/// when mixed with new ones. The blinding polynomial has a zero constant term too,
+/// so that the first commitment, and with it [`share_context`], stays the same.
+pub fn refresh_shares(k: u32, indices: &[u32]) -> (Vec<SecretShare>, Vec<RistrettoPoint>) {
+    let random = |i| {
+        if i == 0 {
+            Scalar::ZERO
+        } else {
+            Scalar::random(&mut OsRng)
+        }
+    };
+    let s_coeffs: Vec<_> = (0..k).map(random).collect();
+    let t_coeffs: Vec<_> = (0..k).map(random).collect();
+    share_polys(&s_coeffs, &t_coeffs, indices)
+}
+
+/// Combine the commitments of two sharings into the commitments of their sum.
+pub fn add_commitments(a: &[RistrettoPoint], b: &[RistrettoPoint]) -> Vec<RistrettoPoint> {
+    assert_eq!(a.len(), b.len());
+    a.iter().zip(b.iter()).map(|(a, b)| a + b).collect()
+}
+
+fn share_scalar(s0: Scalar, k: u32, indices: &[u32]) -> (Vec<SecretShare>, Vec<RistrettoPoint>) {
     let s_coeffs: Vec<_> = (0..k)
         .map(|i| {
             if i == 0 {
@@ -55,12 +137,26 @@ pub fn share_secret(fk: &FileKey, k: u32, n: u32) -> (Vec<SecretShare>, Vec<Rist
             }
         })
         .collect();
-    let t_coeffs: Vec<_> = (0..k).map(|_| Scalar::random(&mut OsRng)).collect();
+    share_poly(&s_coeffs, indices)
+}
+
+/// Share the polynomial with coefficients `s_coeffs`, blinded by a random one of the same degree.
+fn share_poly(s_coeffs: &[Scalar], indices: &[u32]) -> (Vec<SecretShare>, Vec<RistrettoPoint>) {
+    let t_coeffs: Vec<_> = (0..s_coeffs.len())
+        .map(|_| Scalar::random(&mut OsRng))
+        .collect();
+    share_polys(s_coeffs, &t_coeffs, indices)
+}
 
+fn share_polys(
+    s_coeffs: &[Scalar],
+    t_coeffs: &[Scalar],
+    indices: &[u32],
+) -> (Vec<SecretShare>, Vec<RistrettoPoint>) {
     let mut shares = vec![];
-    for index in 1..=n {
-        let s = poly_eval(&s_coeffs, Scalar::from(index));
-        let t = poly_eval(&t_coeffs, Scalar::from(index));
+    for &index in indices {
+        let s = poly_eval(s_coeffs, Scalar::from(index));
+        let t = poly_eval(t_coeffs, Scalar::from(index));
         let share = SecretShare { index, s, t };
         shares.push(share);
     }
@@ -89,30 +185,155 @@ pub fn reconstruct_secret(shares: &[SecretShare]) -> FileKey {
     // L(x) = Σ yᵢ * lᵢ(x)
     // lᵢ(x) = Π (x - xⱼ) / (xᵢ - xⱼ)
     // s_0 = L(0) = Σ yᵢ * lᵢ(0)
-    // lᵢ(0) = Π -xⱼ / (xᵢ - xⱼ)
-    // lᵢ(0) = Π xⱼ / (xⱼ - xᵢ)
 
+    decode(&interpolate(shares))
+}
+
+/// Reconstruct a secret of `len` bytes shared with [`share_bytes`] from the chunk shares of each holder.
+pub fn reconstruct_bytes(holders: &[Vec<SecretShare>], len: usize) -> Vec<u8> {
+    let mut secret = vec![];
+    for c in 0..len.div_ceil(CHUNK_BYTES) {
+        let shares: Vec<_> = holders.iter().map(|h| h[c].clone()).collect();
+        secret.extend_from_slice(&interpolate(&shares).as_bytes()[..CHUNK_BYTES]);
+    }
+    secret.truncate(len);
+    secret
+}
+
+fn interpolate(shares: &[SecretShare]) -> Scalar {
+    let indices: Vec<_> = shares.iter().map(|share| share.index).collect();
     let mut s = Scalar::ZERO;
-    for (i, share) in shares.iter().enumerate() {
-        let mut l_0 = Scalar::ONE;
-        let x_i = Scalar::from(share.index);
-        for (j, other) in shares.iter().enumerate() {
-            if i != j {
-                let x_j = Scalar::from(other.index);
-                if x_j == x_i {
-                    panic!("duplicate share");
-                }
-                l_0 *= x_j * (x_j - x_i).invert();
+    for share in shares {
+        s += share.s * lagrange_coefficient(share.index, &indices, Scalar::ZERO);
+    }
+    s
+}
+
+/// The Lagrange basis polynomial of share `index` among `indices`, evaluated at `x`.
+fn lagrange_coefficient(index: u32, indices: &[u32], x: Scalar) -> Scalar {
+    // lᵢ(x) = Π (x - xⱼ) / (xᵢ - xⱼ), over the other indices j
+    // To reconstruct the secret, x = 0:
+    // lᵢ(0) = Π -xⱼ / (xᵢ - xⱼ)
+    // lᵢ(0) = Π xⱼ / (xⱼ - xᵢ)
+    // To enroll a new index k, x = xₖ, so that Σ yᵢ * lᵢ(xₖ) = L(xₖ).
+    let mut l = Scalar::ONE;
+    let x_i = Scalar::from(index);
+    let mut seen = false;
+    for &other in indices {
+        if other == index {
+            if seen {
+                panic!("duplicate share");
             }
+            seen = true;
+            continue;
         }
-        s += share.s * l_0;
+        let x_j = Scalar::from(other);
+        l *= (x - x_j) * (x_i - x_j).invert();
+    }
+    l
+}
+
+/// The context that encrypted shares are bound to, which refreshes leave unchanged:
+/// the threshold and the commitment to the constant terms.
+pub fn share_context(threshold: u32, coeff_commitments: &[RistrettoPoint]) -> [u8; 32] {
+    header_fingerprint(
+        threshold,
+        &coeff_commitments[..coeff_commitments.len().min(1)],
+    )
+}
+
+/// Identify a header by its threshold and commitments, which shares are verified against.
+pub fn header_fingerprint(threshold: u32, coeff_commitments: &[RistrettoPoint]) -> [u8; 32] {
+    let mut h = Sha256::new();
+    h.update(b"age-threshold header fingerprint");
+    h.update(threshold.to_le_bytes());
+    for c in coeff_commitments {
+        h.update(c.compress().as_bytes());
     }
-    decode(&s)
+    h.finalize().into()
+}
+
+/// A 15-bit identifier for a sharing, derived from its commitments, to tell shares apart.
+pub fn sharing_identifier(coeff_commitments: &[RistrettoPoint]) -> u16 {
+    let mut h = Sha256::new();
+    for c in coeff_commitments {
+        h.update(c.compress().as_bytes());
+    }
+    let digest = h.finalize();
+    u16::from_be_bytes([digest[0], digest[1]]) >> 1
+}
+
+/// Split a helper's contribution towards a share at `new_index` into one piece per helper.
+///
+/// The contribution is the helper's share weighted by its Lagrange coefficient at `new_index`.
+/// Pieces are uniformly random on their own, but all the pieces of all the helpers sum up to
+/// the share at `new_index`. Each helper keeps one piece and sends the others privately;
+/// helpers then sum what they hold with [`sum_shares`] and hand the result to the new holder,
+/// who sums those again. Nobody sees another helper's share or the secret.
+pub fn enrollment_pieces(
+    share: &SecretShare,
+    helpers: &[u32],
+    new_index: u32,
+) -> Result<Vec<SecretShare>, &'static str> {
+    if new_index == 0 || helpers.contains(&new_index) {
+        return Err("invalid new index");
+    }
+    if !helpers.contains(&share.index) {
+        return Err("share is not among the helpers");
+    }
+    for (i, h) in helpers.iter().enumerate() {
+        if helpers[..i].contains(h) {
+            return Err("duplicate helper");
+        }
+    }
+
+    let l = lagrange_coefficient(share.index, helpers, Scalar::from(new_index));
+    let mut s_rest = share.s * l;
+    let mut t_rest = share.t * l;
+    let mut pieces = vec![];
+    for _ in 1..helpers.len() {
+        let s = Scalar::random(&mut OsRng);
+        let t = Scalar::random(&mut OsRng);
+        s_rest -= s;
+        t_rest -= t;
+        pieces.push(SecretShare {
+            index: new_index,
+            s,
+            t,
+        });
+    }
+    pieces.push(SecretShare {
+        index: new_index,
+        s: s_rest,
+        t: t_rest,
+    });
+    Ok(pieces)
+}
+
+/// Add up shares for the same index, as done at each step of the enrollment protocol.
+pub fn sum_shares(shares: &[SecretShare]) -> Result<SecretShare, &'static str> {
+    let index = shares.first().ok_or("no shares")?.index;
+    let mut sum = SecretShare {
+        index,
+        s: Scalar::ZERO,
+        t: Scalar::ZERO,
+    };
+    for share in shares {
+        if share.index != index {
+            return Err("mismatched share indices");
+        }
+        sum.s += share.s;
+        sum.t += share.t;
+    }
+    Ok(sum)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{reconstruct_secret, share_secret, verify_share};
+    use super::{
+        add_commitments, enrollment_pieces, reconstruct_bytes, reconstruct_secret, refresh_shares,
+        share_bytes, share_context, share_secret, sum_shares, verify_share,
+    };
     use age_core::format::{FileKey, FILE_KEY_BYTES};
     use age_core::secrecy::ExposeSecret;
 
@@ -139,4 +360,87 @@ mod tests {
             assert!(verify_share(share, &commitments));
         }
     }
+
+    #[test]
+    fn test_share_bytes() {
+        for len in [1, 31, 32, 64, 100] {
+            let secret: Vec<u8> = (0..len).map(|i| 0xff - i as u8).collect();
+            let (holders, commitments) = share_bytes(&secret, 3, 5);
+            assert_eq!(holders.len(), 5);
+            for holder in &holders {
+                for (share, c) in holder.iter().zip(commitments.iter()) {
+                    assert!(verify_share(share, c));
+                }
+            }
+            assert_eq!(reconstruct_bytes(&holders[1..4], len), secret);
+            assert_ne!(reconstruct_bytes(&holders[3..], len), secret);
+        }
+    }
+
+    #[test]
+    fn test_enrollment() {
+        let actual = [0x9; FILE_KEY_BYTES];
+        let t = 3;
+        let n = 5;
+        let (shares, commitments) = share_secret(&FileKey::from(actual), t, n);
+
+        let helpers = [1, 3, 5];
+        let mut held = vec![vec![]; helpers.len()];
+        for &h in &helpers {
+            let pieces = enrollment_pieces(&shares[h as usize - 1], &helpers, 7).unwrap();
+            for (j, piece) in pieces.into_iter().enumerate() {
+                held[j].push(piece);
+            }
+        }
+        let sums: Vec<_> = held.iter().map(|v| sum_shares(v).unwrap()).collect();
+        let new_share = sum_shares(&sums).unwrap();
+        assert_eq!(new_share.index, 7);
+        assert!(verify_share(&new_share, &commitments));
+
+        let result = reconstruct_secret(&[shares[1].clone(), shares[3].clone(), new_share]);
+        assert_eq!(&actual, result.expose_secret());
+    }
+
+    #[test]
+    fn test_enrollment_invalid() {
+        let (shares, _) = share_secret(&FileKey::from([0x9; FILE_KEY_BYTES]), 2, 3);
+        assert!(enrollment_pieces(&shares[0], &[1, 2], 2).is_err());
+        assert!(enrollment_pieces(&shares[0], &[2, 3], 4).is_err());
+        assert!(enrollment_pieces(&shares[0], &[1, 1], 4).is_err());
+        assert!(enrollment_pieces(&shares[0], &[1, 2], 0).is_err());
+    }
+
+    #[test]
+    fn test_refresh() {
+        let actual = [0x9; FILE_KEY_BYTES];
+        let t = 3;
+        let n = 5;
+        let (shares, commitments) = share_secret(&FileKey::from(actual), t, n);
+        let (deltas, delta_commitments) = refresh_shares(t, &[1, 2, 3, 4, 5]);
+        let old_commitments = commitments;
+        let commitments = add_commitments(&old_commitments, &delta_commitments);
+
+        let refreshed: Vec<_> = shares
+            .iter()
+            .zip(deltas.iter())
+            .map(|(s, d)| sum_shares(&[s.clone(), d.clone()]).unwrap())
+            .collect();
+        assert_eq!(
+            share_context(t, &commitments),
+            share_context(t, &old_commitments)
+        );
+        for (old, new) in shares.iter().zip(refreshed.iter()) {
+            assert!(!verify_share(old, &commitments));
+            assert!(verify_share(new, &commitments));
+        }
+
+        let result = reconstruct_secret(&refreshed[2..]);
+        assert_eq!(&actual, result.expose_secret());
+        let mixed = reconstruct_secret(&[
+            shares[0].clone(),
+            refreshed[1].clone(),
+            refreshed[2].clone(),
+        ]);
+        assert_ne!(&actual, mixed.expose_secret());
+    }
 }
@@ -0,0 +1,189 @@
+//! Committee keys, generated and used without the group secret ever existing in one place.
+//!
+//! Key generation follows Gennaro, Jarecki, Krawczyk and Rabin's "Secure Distributed Key
+//! Generation for Discrete-Log Based Cryptosystems" (EUROCRYPT '99). Every member deals a random
+//! polynomial with Pedersen commitments, like [`super::share_secret`]. Once every member has
+//! checked the shares it received, dealers reveal commitments `a_k · B` to their coefficients,
+//! which members check their shares against again. A member's key share `x_i` is the sum of the
+//! shares it received, and the group key `Y` is the sum of the revealed constant terms.
+//!
+//! Files are encrypted to `Y` with ElGamal: the header holds `R = r · B`, and the file key is
+//! derived from `r · Y`. Each member decrypts partially as `x_i · R`, with a proof against its
+//! verification key `x_i · B`, which anyone can compute from the summed commitments. Any
+//! threshold of partial decryptions interpolate to `r · Y`.
+
+use age_core::format::{FileKey, FILE_KEY_BYTES};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+
+use super::dleq;
+use super::{eval_commitments, share_poly};
+use crate::types::{
+    CommitteeIdentity, CommitteeRecipient, CommitteeReveal, PartialDecryption, SecretShare,
+};
+
+const PARTIAL_LABEL: &[u8] = b"age-threshold committee partial decryption";
+
+/// Deal a random polynomial of degree `k - 1` to members 1 to `n`.
+///
+/// Returns the coefficients, to keep until [`reveal`], the shares and their Pedersen commitments.
+pub fn deal(k: u32, n: u32) -> (Vec<Scalar>, Vec<SecretShare>, Vec<RistrettoPoint>) {
+    let coeffs: Vec<_> = (0..k).map(|_| Scalar::random(&mut OsRng)).collect();
+    let indices: Vec<_> = (1..=n).collect();
+    let (shares, commitments) = share_poly(&coeffs, &indices);
+    (coeffs, shares, commitments)
+}
+
+/// Commit to the coefficients of a dealt polynomial with the base point.
+pub fn reveal(coeffs: &[Scalar]) -> Vec<RistrettoPoint> {
+    coeffs
+        .iter()
+        .map(|a| RISTRETTO_BASEPOINT_POINT * a)
+        .collect()
+}
+
+/// Check a share received from a dealer against what the dealer revealed.
+pub fn verify_reveal(share: &SecretShare, reveal: &CommitteeReveal) -> bool {
+    share.index != 0
+        && RISTRETTO_BASEPOINT_POINT * share.s
+            == eval_commitments(&reveal.commitments, Scalar::from(share.index))
+}
+
+/// The public counterpart `x_i · B` of member `index`'s key share.
+pub fn verification_key(recipient: &CommitteeRecipient, index: u32) -> RistrettoPoint {
+    eval_commitments(&recipient.commitments, Scalar::from(index))
+}
+
+/// Pick an ephemeral key for a new file. Returns `R` and the shared secret `r · Y`.
+pub fn encapsulate(recipient: &CommitteeRecipient) -> (RistrettoPoint, RistrettoPoint) {
+    let r = Scalar::random(&mut OsRng);
+    (RISTRETTO_BASEPOINT_POINT * r, recipient.group_key() * r)
+}
+
+/// Compute a member's partial decryption `x_i · R` of the file with ephemeral key `R`.
+pub fn partial_decrypt(
+    identity: &CommitteeIdentity,
+    ephemeral: &RistrettoPoint,
+) -> PartialDecryption {
+    let x = identity.secret();
+    let decrypted = ephemeral * x;
+    let proof = dleq::prove(
+        PARTIAL_LABEL,
+        (&RISTRETTO_BASEPOINT_POINT, &(RISTRETTO_BASEPOINT_POINT * x)),
+        (ephemeral, &decrypted),
+        x,
+    );
+    PartialDecryption {
+        index: identity.index,
+        decrypted,
+        proof,
+    }
+}
+
+/// Check a partial decryption from [`partial_decrypt`]. Needs no secret.
+pub fn verify_partial(
+    recipient: &CommitteeRecipient,
+    ephemeral: &RistrettoPoint,
+    partial: &PartialDecryption,
+) -> bool {
+    partial.index != 0
+        && dleq::verify(
+            PARTIAL_LABEL,
+            (
+                &RISTRETTO_BASEPOINT_POINT,
+                &verification_key(recipient, partial.index),
+            ),
+            (ephemeral, &partial.decrypted),
+            &partial.proof,
+        )
+}
+
+/// Interpolate partial decryptions with distinct indices to the shared secret `r · Y`.
+pub fn combine(partials: &[PartialDecryption]) -> RistrettoPoint {
+    let points: Vec<_> = partials.iter().map(|p| (p.index, p.decrypted)).collect();
+    super::pvss::combine(&points)
+}
+
+/// Identify a file encrypted to a committee, for members to check before decrypting partially.
+pub fn header_fingerprint(
+    threshold: u32,
+    coeff_commitments: &[RistrettoPoint],
+    ephemeral: &RistrettoPoint,
+) -> [u8; 32] {
+    let mut h = Sha256::new();
+    h.update(b"age-threshold committee header fingerprint");
+    h.update(threshold.to_le_bytes());
+    for c in coeff_commitments {
+        h.update(c.compress().as_bytes());
+    }
+    h.update(ephemeral.compress().as_bytes());
+    h.finalize().into()
+}
+
+/// Derive the file key from the ephemeral key and the shared secret.
+pub fn file_key(ephemeral: &RistrettoPoint, shared: &RistrettoPoint) -> FileKey {
+    let mut h = Sha256::new();
+    h.update(b"age-threshold committee file key");
+    h.update(ephemeral.compress().as_bytes());
+    h.update(shared.compress().as_bytes());
+    let mut key = [0; FILE_KEY_BYTES];
+    key.copy_from_slice(&h.finalize()[..FILE_KEY_BYTES]);
+    FileKey::from(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use curve25519_dalek::scalar::Scalar;
+
+    use super::{
+        combine, deal, encapsulate, partial_decrypt, reveal, verify_partial, verify_reveal,
+    };
+    use crate::crypto::{add_commitments, verify_share};
+    use crate::types::{CommitteeIdentity, CommitteeRecipient, CommitteeReveal};
+
+    #[test]
+    fn test_keygen_decrypt() {
+        let (k, n) = (2, 3);
+        let deals: Vec<_> = (0..n).map(|_| deal(k, n)).collect();
+        let reveals: Vec<_> = deals
+            .iter()
+            .enumerate()
+            .map(|(j, (coeffs, _, _))| CommitteeReveal {
+                dealer: j as u32 + 1,
+                commitments: reveal(coeffs),
+            })
+            .collect();
+
+        let mut commitments = reveals[0].commitments.clone();
+        for r in &reveals[1..] {
+            commitments = add_commitments(&commitments, &r.commitments);
+        }
+        let recipient = CommitteeRecipient { commitments };
+        let mut identities = vec![];
+        for i in 0..n as usize {
+            let mut x = Scalar::ZERO;
+            for ((_, shares, pedersen), r) in deals.iter().zip(&reveals) {
+                assert!(verify_share(&shares[i], pedersen));
+                assert!(verify_reveal(&shares[i], r));
+                x += shares[i].s;
+            }
+            identities.push(CommitteeIdentity::new(i as u32 + 1, x, recipient.clone()).unwrap());
+        }
+
+        let (ephemeral, shared) = encapsulate(&recipient);
+        let partials: Vec<_> = identities
+            .iter()
+            .map(|i| partial_decrypt(i, &ephemeral))
+            .collect();
+        assert!(partials
+            .iter()
+            .all(|p| verify_partial(&recipient, &ephemeral, p)));
+        assert!(!verify_partial(&recipient, &shared, &partials[0]));
+        assert_eq!(combine(&partials[1..]), shared);
+        assert_eq!(combine(&[partials[2].clone(), partials[0].clone()]), shared);
+        assert_ne!(combine(&partials[..1]), shared);
+    }
+}
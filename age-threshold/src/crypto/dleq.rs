@@ -0,0 +1,91 @@
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use rand::rngs::OsRng;
+use sha2::Sha512;
+
+/// A non-interactive Chaum-Pedersen proof that `h1 = w · g1` and `h2 = w · g2` for the same `w`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DleqProof {
+    pub c: Scalar,
+    pub z: Scalar,
+}
+
+impl DleqProof {
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut buf = [0; 64];
+        buf[..32].copy_from_slice(self.c.as_bytes());
+        buf[32..].copy_from_slice(self.z.as_bytes());
+        buf
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, &'static str> {
+        if bytes.len() != 64 {
+            return Err("invalid proof length");
+        }
+        let scalar = |b: &[u8]| {
+            Option::from(Scalar::from_canonical_bytes(b.try_into().unwrap())).ok_or("invalid proof")
+        };
+        Ok(DleqProof {
+            c: scalar(&bytes[..32])?,
+            z: scalar(&bytes[32..])?,
+        })
+    }
+}
+
+/// Prove knowledge of `w`, binding the proof to `label`.
+pub fn prove(
+    label: &[u8],
+    (g1, h1): (&RistrettoPoint, &RistrettoPoint),
+    (g2, h2): (&RistrettoPoint, &RistrettoPoint),
+    w: &Scalar,
+) -> DleqProof {
+    let r = Scalar::random(&mut OsRng);
+    let (a1, a2) = (g1 * r, g2 * r);
+    let c = challenge(label, [g1, h1, g2, h2, &a1, &a2]);
+    DleqProof { c, z: r - c * w }
+}
+
+pub fn verify(
+    label: &[u8],
+    (g1, h1): (&RistrettoPoint, &RistrettoPoint),
+    (g2, h2): (&RistrettoPoint, &RistrettoPoint),
+    proof: &DleqProof,
+) -> bool {
+    let a1 = g1 * proof.z + h1 * proof.c;
+    let a2 = g2 * proof.z + h2 * proof.c;
+    challenge(label, [g1, h1, g2, h2, &a1, &a2]) == proof.c
+}
+
+fn challenge(label: &[u8], points: [&RistrettoPoint; 6]) -> Scalar {
+    let mut buf = label.to_vec();
+    for p in points {
+        buf.extend_from_slice(p.compress().as_bytes());
+    }
+    Scalar::hash_from_bytes::<Sha512>(&buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+
+    use super::{prove, verify, DleqProof};
+
+    #[test]
+    fn test_prove_verify() {
+        let g1 = RISTRETTO_BASEPOINT_POINT;
+        let g2 = RistrettoPoint::random(&mut OsRng);
+        let w = Scalar::random(&mut OsRng);
+        let (h1, h2) = (g1 * w, g2 * w);
+        let proof = prove(b"test", (&g1, &h1), (&g2, &h2), &w);
+        assert!(verify(b"test", (&g1, &h1), (&g2, &h2), &proof));
+        assert_eq!(DleqProof::from_bytes(&proof.to_bytes()), Ok(proof));
+
+        assert!(!verify(b"other", (&g1, &h1), (&g2, &h2), &proof));
+        let h2 = g2 * (w + Scalar::ONE);
+        let proof = prove(b"test", (&g1, &h1), (&g2, &h2), &w);
+        assert!(!verify(b"test", (&g1, &h1), (&g2, &h2), &proof));
+    }
+}
@@ -0,0 +1,173 @@
+//! Verifiable secret sharing with hash commitments, which unlike Pedersen commitments rest on no
+//! discrete logarithm assumption and so stay sound against a quantum adversary.
+//!
+//! This follows the hash-based scheme of Atapoor, Baghery, Cozzo and Pedersen's "VSS from
+//! Distributed ZK Proofs and Applications" (ASIACRYPT 2023). The dealer shares the secret with a
+//! polynomial `f` of degree `k - 1` and masks it with a uniformly random polynomial `g` of the
+//! same degree. Each share `(f(i), g(i))` is committed to with a SHA-256 digest, and a challenge
+//! `d` is derived from all the digests. The dealer then publishes the masked polynomial
+//! `g + d · f`, which says nothing about `f` since `g` is uniformly random.
+//!
+//! A holder checks that its share matches its digest and that `g(i) + d · f(i)` is the masked
+//! polynomial at `i`. Shares that do not lie on polynomials of degree `k - 1` pass for at most
+//! one challenge, which the dealer can only aim for by finding a collision of the hash.
+
+use age_core::format::FileKey;
+use curve25519_dalek::scalar::Scalar;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256, Sha512};
+
+use super::{encode, poly_eval};
+use crate::types::{HashCommitments, SecretShare};
+
+const DIGEST_LABEL: &[u8] = b"age-threshold hash commitment";
+const CHALLENGE_LABEL: &[u8] = b"age-threshold hash commitment challenge";
+const FINGERPRINT_LABEL: &[u8] = b"age-threshold hash header fingerprint";
+
+/// Share a file key among holders 1 to `n`, any `k` of which can reconstruct it.
+pub fn share_secret(fk: &FileKey, k: u32, n: u32) -> (Vec<SecretShare>, HashCommitments) {
+    let mut f = vec![encode(fk)];
+    f.extend((1..k).map(|_| Scalar::random(&mut OsRng)));
+    let g: Vec<_> = (0..k).map(|_| Scalar::random(&mut OsRng)).collect();
+    share_poly(&f, &g, n)
+}
+
+/// Share with the given secret polynomial `f` and mask polynomial `g`.
+fn share_poly(f: &[Scalar], g: &[Scalar], n: u32) -> (Vec<SecretShare>, HashCommitments) {
+    let shares: Vec<_> = (1..=n)
+        .map(|index| SecretShare {
+            index,
+            s: poly_eval(f, Scalar::from(index)),
+            t: poly_eval(g, Scalar::from(index)),
+        })
+        .collect();
+    let digests: Vec<_> = shares.iter().map(digest).collect();
+    let d = challenge(f.len(), &digests);
+    let masked = g.iter().zip(f).map(|(g, f)| g + d * f).collect();
+    (shares, HashCommitments { masked, digests })
+}
+
+/// Check that a share matches its digest and lies on the same polynomial as the others.
+pub fn verify_share(share: &SecretShare, commitments: &HashCommitments) -> bool {
+    let Some(expected) = (share.index as usize)
+        .checked_sub(1)
+        .and_then(|i| commitments.digests.get(i))
+    else {
+        return false;
+    };
+    let d = challenge(commitments.masked.len(), &commitments.digests);
+    digest(share) == *expected
+        && share.t + d * share.s == poly_eval(&commitments.masked, Scalar::from(share.index))
+}
+
+/// A fingerprint of a header with hash commitments, like [`super::header_fingerprint`].
+pub fn header_fingerprint(threshold: u32, commitments: &HashCommitments) -> [u8; 32] {
+    let mut h = Sha256::new();
+    h.update(FINGERPRINT_LABEL);
+    h.update(threshold.to_le_bytes());
+    for m in &commitments.masked {
+        h.update(m.as_bytes());
+    }
+    for d in &commitments.digests {
+        h.update(d);
+    }
+    h.finalize().into()
+}
+
+fn digest(share: &SecretShare) -> [u8; 32] {
+    let mut h = Sha256::new();
+    h.update(DIGEST_LABEL);
+    h.update(share.index.to_le_bytes());
+    h.update(share.s.as_bytes());
+    h.update(share.t.as_bytes());
+    h.finalize().into()
+}
+
+fn challenge(k: usize, digests: &[[u8; 32]]) -> Scalar {
+    let mut buf = CHALLENGE_LABEL.to_vec();
+    buf.extend_from_slice(&(k as u32).to_le_bytes());
+    for d in digests {
+        buf.extend_from_slice(d);
+    }
+    Scalar::hash_from_bytes::<Sha512>(&buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use age_core::format::{FileKey, FILE_KEY_BYTES};
+    use age_core::secrecy::ExposeSecret;
+    use curve25519_dalek::scalar::Scalar;
+    use hex_literal::hex;
+
+    use super::{challenge, digest, share_poly, share_secret, verify_share};
+    use crate::crypto::reconstruct_secret;
+    use crate::types::{HashCommitments, SecretShare};
+
+    #[test]
+    fn test_share_verify_reconstruct() {
+        let (shares, commitments) = share_secret(&FileKey::from([7; FILE_KEY_BYTES]), 2, 3);
+        assert!(shares.iter().all(|s| verify_share(s, &commitments)));
+        assert_eq!(
+            reconstruct_secret(&shares[1..]).expose_secret(),
+            &[7; FILE_KEY_BYTES]
+        );
+
+        let mut tampered = shares[0].clone();
+        tampered.s += Scalar::ONE;
+        assert!(!verify_share(&tampered, &commitments));
+        let mut moved = shares[0].clone();
+        moved.index = 4;
+        assert!(!verify_share(&moved, &commitments));
+    }
+
+    #[test]
+    fn test_vectors() {
+        // f(x) = 1 + 2x and g(x) = 3 + 4x, shared among 3 holders.
+        let f = [Scalar::from(1u32), Scalar::from(2u32)];
+        let g = [Scalar::from(3u32), Scalar::from(4u32)];
+        let (shares, commitments) = share_poly(&f, &g, 3);
+        assert_eq!(
+            commitments.digests,
+            [
+                hex!("57849284c2ebece5ceff3dc99e4a3831cccc65d32975d1a5d6d6274f27411c07"),
+                hex!("f495c35622323b2628511572539f201d636cc16afdf9d287095569459787749a"),
+                hex!("979fd787928f989ce929a06bc42ba282b1b7b6559bfbfddb468857b18cc5edf0"),
+            ]
+        );
+        // With the challenge d, the masked polynomial is (3 + d) + (4 + 2d)x.
+        let masked: Vec<_> = commitments.masked.iter().map(|m| m.to_bytes()).collect();
+        assert_eq!(
+            masked,
+            [
+                hex!("ac065ce0625b1b5bee752d2846ef9b610c0ee844ad477c7b4ee60eb002af8f01"),
+                hex!("560db8c0c5b636b6dceb5a508cde37c3181cd0895a8ff8f69ccc1d60055e1f03"),
+            ]
+        );
+        assert!(shares.iter().all(|s| verify_share(s, &commitments)));
+    }
+
+    #[test]
+    fn test_inconsistent_dealer() {
+        // Holder 3 gets f(3) = 8 while holders 1 and 2 get a line through f(1) = 1, f(2) = 2.
+        let shares: Vec<_> = [(1u32, 1u32, 5u32), (2, 2, 6), (3, 8, 7)]
+            .iter()
+            .map(|&(index, s, t)| SecretShare {
+                index,
+                s: Scalar::from(s),
+                t: Scalar::from(t),
+            })
+            .collect();
+        let digests: Vec<_> = shares.iter().map(digest).collect();
+        let d = challenge(2, &digests);
+        // The best the dealer can do is a masked line through the first two holders.
+        let v1 = shares[0].t + d * shares[0].s;
+        let v2 = shares[1].t + d * shares[1].s;
+        let commitments = HashCommitments {
+            masked: vec![v1 + v1 - v2, v2 - v1],
+            digests,
+        };
+        assert!(verify_share(&shares[0], &commitments));
+        assert!(verify_share(&shares[1], &commitments));
+        assert!(!verify_share(&shares[2], &commitments));
+    }
+}
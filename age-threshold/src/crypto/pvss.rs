@@ -0,0 +1,146 @@
+//! Publicly verifiable secret sharing, after Schoenmakers' "A Simple Publicly Verifiable
+//! Secret Sharing Scheme and its Application to Electronic Voting" (CRYPTO '99).
+//!
+//! The dealer picks a random polynomial `p` and commits to its coefficients with `H`.
+//! Holder `i`, with key `y_i = x_i · B`, receives `Y_i = p(i) · y_i` and a proof that
+//! `Y_i` and the commitments hide the same `p(i)`, which anyone can check.
+//! The holder recovers `p(i) · B` from `Y_i` with its secret key, and any threshold of
+//! those interpolate to the secret `p(0) · B`, from which the file key is derived.
+
+use age_core::format::{FileKey, FILE_KEY_BYTES};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+
+use super::dleq::{self, DleqProof};
+use super::{eval_commitments, lagrange_coefficient, poly_eval, GENERATORS};
+use crate::types::{PvssShare, RistrettoIdentity, RistrettoRecipient};
+
+const SHARE_LABEL: &[u8] = b"age-threshold pvss share";
+const DECRYPTION_LABEL: &[u8] = b"age-threshold pvss decryption";
+
+/// Share a fresh random secret among `recipients`, any `k` of which can recover it.
+///
+/// Returns the secret, the commitments to the coefficients and one share per recipient,
+/// at indices 1 to n.
+pub fn deal(
+    k: u32,
+    recipients: &[RistrettoRecipient],
+) -> (RistrettoPoint, Vec<RistrettoPoint>, Vec<PvssShare>) {
+    let h = GENERATORS.1;
+    let coeffs: Vec<_> = (0..k).map(|_| Scalar::random(&mut OsRng)).collect();
+    let commitments: Vec<_> = coeffs.iter().map(|a| h * a).collect();
+
+    let mut shares = vec![];
+    for (i, r) in recipients.iter().enumerate() {
+        let index = i as u32 + 1;
+        let p_i = poly_eval(&coeffs, Scalar::from(index));
+        let encrypted = r.0 * p_i;
+        let proof = dleq::prove(SHARE_LABEL, (&h, &(h * p_i)), (&r.0, &encrypted), &p_i);
+        shares.push(PvssShare {
+            index,
+            recipient: *r,
+            encrypted,
+            proof,
+        });
+    }
+    (RISTRETTO_BASEPOINT_POINT * coeffs[0], commitments, shares)
+}
+
+/// Check that an encrypted share is consistent with the commitments. Needs no secret.
+pub fn verify_share(share: &PvssShare, commitments: &[RistrettoPoint]) -> bool {
+    let h = GENERATORS.1;
+    let committed = eval_commitments(commitments, Scalar::from(share.index));
+    share.index != 0
+        && dleq::verify(
+            SHARE_LABEL,
+            (&h, &committed),
+            (&share.recipient.0, &share.encrypted),
+            &share.proof,
+        )
+}
+
+/// Recover `p(i) · B` from a share, with a proof that it was decrypted correctly.
+pub fn decrypt_share(
+    identity: &RistrettoIdentity,
+    share: &PvssShare,
+) -> (RistrettoPoint, DleqProof) {
+    let x = identity.secret();
+    let decrypted = share.encrypted * x.invert();
+    // y = x · B and Y = x · decrypted
+    let proof = dleq::prove(
+        DECRYPTION_LABEL,
+        (&RISTRETTO_BASEPOINT_POINT, &share.recipient.0),
+        (&decrypted, &share.encrypted),
+        x,
+    );
+    (decrypted, proof)
+}
+
+/// Check a decrypted share from [`decrypt_share`]. Needs no secret.
+pub fn verify_decryption(share: &PvssShare, decrypted: &RistrettoPoint, proof: &DleqProof) -> bool {
+    dleq::verify(
+        DECRYPTION_LABEL,
+        (&RISTRETTO_BASEPOINT_POINT, &share.recipient.0),
+        (decrypted, &share.encrypted),
+        proof,
+    )
+}
+
+/// Interpolate decrypted shares, given with their indices, to the secret.
+pub fn combine(decrypted: &[(u32, RistrettoPoint)]) -> RistrettoPoint {
+    let indices: Vec<_> = decrypted.iter().map(|(i, _)| *i).collect();
+    decrypted
+        .iter()
+        .map(|(i, d)| d * lagrange_coefficient(*i, &indices, Scalar::ZERO))
+        .sum()
+}
+
+/// Derive the file key from the shared secret.
+pub fn file_key(secret: &RistrettoPoint) -> FileKey {
+    let mut h = Sha256::new();
+    h.update(b"age-threshold pvss file key");
+    h.update(secret.compress().as_bytes());
+    let mut key = [0; FILE_KEY_BYTES];
+    key.copy_from_slice(&h.finalize()[..FILE_KEY_BYTES]);
+    FileKey::from(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{combine, deal, decrypt_share, verify_decryption, verify_share};
+    use crate::types::RistrettoIdentity;
+
+    #[test]
+    fn test_deal_verify_combine() {
+        let identities: Vec<_> = (0..5).map(|_| RistrettoIdentity::generate()).collect();
+        let recipients: Vec<_> = identities.iter().map(|i| i.to_public()).collect();
+        let (secret, commitments, shares) = deal(3, &recipients);
+        assert!(shares.iter().all(|s| verify_share(s, &commitments)));
+
+        let mut decrypted = vec![];
+        for i in [4, 1, 2] {
+            let (d, proof) = decrypt_share(&identities[i], &shares[i]);
+            assert!(verify_decryption(&shares[i], &d, &proof));
+            assert!(!verify_decryption(&shares[0], &d, &proof));
+            decrypted.push((shares[i].index, d));
+        }
+        assert_eq!(combine(&decrypted), secret);
+        assert_ne!(combine(&decrypted[..2]), secret);
+    }
+
+    #[test]
+    fn test_reject_inconsistent_share() {
+        let identities: Vec<_> = (0..3).map(|_| RistrettoIdentity::generate()).collect();
+        let recipients: Vec<_> = identities.iter().map(|i| i.to_public()).collect();
+        let (_, commitments, mut shares) = deal(2, &recipients);
+
+        // A dealer handing out garbage is caught from public data alone.
+        shares[1].encrypted += recipients[1].0;
+        assert!(!verify_share(&shares[1], &commitments));
+        shares[2].index = 1;
+        assert!(!verify_share(&shares[2], &commitments));
+    }
+}
@@ -0,0 +1,95 @@
+//! Enrollment of a new holder by the holders of a file, without reconstructing its key.
+
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use age::cli_common::UiCallbacks;
+use age_core::secrecy::Zeroize;
+use nom_bufreader::bufreader::BufReader;
+use std::io;
+use std::io::prelude::*;
+
+use crate::header::{read_header, write_header};
+use crate::shares::encrypt_share;
+use crate::{crypto, types};
+
+const PIECE_VERSION_LINE: &[u8] = b"age-threshold/enrollment-piece/v1\n";
+
+/// Encrypt an enrollment piece, or a sum of pieces, to the holder it is meant for.
+///
+/// The pieces of enough helpers add up to their shares, so they must not travel in the clear.
+pub fn seal_piece(
+    piece: &types::SecretShare,
+    recipient: &types::AgeRecipient,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut text = String::from_utf8(PIECE_VERSION_LINE.to_vec()).unwrap();
+    text.push_str(&piece.to_bech32());
+    text.push('\n');
+    let recipient = recipient
+        .to_recipient(UiCallbacks)
+        .map_err(io::Error::other)?;
+    let encryptor = age::Encryptor::with_recipients(vec![recipient]).unwrap();
+    let output = ArmoredWriter::wrap_output(output, Format::AsciiArmor)?;
+    let mut w = encryptor.wrap_output(output).map_err(io::Error::other)?;
+    w.write_all(text.as_bytes())?;
+    text.zeroize();
+    w.finish()?.finish()?;
+    Ok(())
+}
+
+/// Decrypt a piece sealed with [`seal_piece`].
+pub fn open_piece(
+    identities: &[types::AgeIdentity],
+    input: &mut impl Read,
+) -> io::Result<types::SecretShare> {
+    let age::Decryptor::Recipients(decryptor) =
+        age::Decryptor::new(ArmoredReader::new(input)).map_err(io::Error::other)?
+    else {
+        return Err(io::Error::other("piece is not encrypted to a recipient"));
+    };
+    let identities = identities
+        .iter()
+        .map(|i| i.to_identity(UiCallbacks))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io::Error::other)?;
+    let mut text = String::new();
+    decryptor
+        .decrypt(identities.iter().map(|i| i.as_ref()))
+        .map_err(io::Error::other)?
+        .read_to_string(&mut text)?;
+    let piece = match text.strip_prefix(std::str::from_utf8(PIECE_VERSION_LINE).unwrap()) {
+        Some(body) => types::SecretShare::from_bech32(body.trim()).map_err(io::Error::other),
+        None => Err(io::Error::other("not an enrollment piece")),
+    };
+    text.zeroize();
+    piece
+}
+
+/// Append a new share group for `share`, typically the result of the enrollment protocol.
+///
+/// The share must be consistent with the commitments of the file and its index must be unused.
+pub fn enroll(
+    share: &types::SecretShare,
+    recipients: &[types::AgeRecipient],
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    if recipients.is_empty() {
+        return Err(io::Error::other("no recipients"));
+    }
+    let mut input = BufReader::new(input);
+    let mut header = read_header(&mut input)?;
+
+    let commitments = header.commitments.pedersen().map_err(io::Error::other)?;
+    if !crypto::verify_share(share, commitments) {
+        return Err(io::Error::other("invalid share"));
+    }
+    if header.enc_shares.iter().any(|es| es.index == share.index) {
+        return Err(io::Error::other("share index already in use"));
+    }
+    let es = encrypt_share(share, recipients, &header)?;
+    header.enc_shares.push(es);
+
+    let output = write_header(output, &header)?;
+    io::copy(&mut input, output)?;
+    Ok(())
+}
@@ -1 +1,6 @@
-pub const VERSION_LINE: &[u8] = b"bbjubjub.fr/age-threshold/v0\n";
+pub const VERSION_LINE_V0: &[u8] = b"bbjubjub.fr/age-threshold/v0\n";
+pub const VERSION_LINE_V1: &[u8] = b"bbjubjub.fr/age-threshold/v1\n";
+pub const PVSS_VERSION_LINE: &[u8] = b"bbjubjub.fr/age-threshold/pvss/v0\n";
+pub const COMMITTEE_VERSION_LINE: &[u8] = b"bbjubjub.fr/age-threshold/committee/v0\n";
+pub const AGE_VERSION_LINE: &[u8] = b"age-encryption.org/v1\n";
+pub const AGE_MAC_PREFIX: &[u8] = b"---";
@@ -1,24 +1,39 @@
-use nom::bytes::streaming::tag;
+use nom::branch::alt;
+use nom::bytes::streaming::{tag, take_while1};
+use nom::combinator::value;
 use nom::error::{Error, ErrorKind};
 use nom::multi::many_till;
 use nom::IResult;
 
 use age_core::format::read::age_stanza;
 
-use base64::{engine::general_purpose::STANDARD, Engine as _};
+use base64::{
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD},
+    Engine as _,
+};
 
-use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
 
-use crate::format::common::VERSION_LINE;
-use crate::types::{EncShare, Header};
+use crate::crypto::dleq::DleqProof;
+use crate::format::common::{
+    AGE_MAC_PREFIX, AGE_VERSION_LINE, COMMITTEE_VERSION_LINE, PVSS_VERSION_LINE, VERSION_LINE_V0,
+    VERSION_LINE_V1,
+};
+use crate::types::{
+    AgeHeader, Commitments, CommitteeHeader, EncShare, Extension, HashCommitments, Header,
+    HeaderVersion, PvssHeader, PvssShare, RistrettoRecipient,
+};
 
 fn base64decode(data: &str) -> Result<Vec<u8>, base64::DecodeError> {
     STANDARD.decode(data)
 }
 
-fn version_line(input: &[u8]) -> IResult<&[u8], ()> {
-    let (input, _) = tag(VERSION_LINE)(input)?;
-    Ok((input, ()))
+fn version_line(input: &[u8]) -> IResult<&[u8], HeaderVersion> {
+    alt((
+        value(HeaderVersion::V0, tag(VERSION_LINE_V0)),
+        value(HeaderVersion::V1, tag(VERSION_LINE_V1)),
+    ))(input)
 }
 
 fn hmac_line(input: &[u8]) -> IResult<&[u8], ()> {
@@ -27,8 +42,8 @@ fn hmac_line(input: &[u8]) -> IResult<&[u8], ()> {
     Ok((input, ()))
 }
 
-pub fn header(input: &[u8]) -> IResult<&[u8], Header> {
-    let (input, ()) = version_line(input)?;
+/// The `threshold` stanza that starts every header.
+fn threshold(input: &[u8]) -> IResult<&[u8], usize> {
     let (input, stanza) = age_stanza(input)?;
     if stanza.tag != "threshold" {
         return Err(nom::Err::Failure(Error::new(input, ErrorKind::Tag)));
@@ -36,27 +51,93 @@ pub fn header(input: &[u8]) -> IResult<&[u8], Header> {
     let threshold = stanza.args[0]
         .parse()
         .map_err(|_| nom::Err::Error(Error::new(input, ErrorKind::Satisfy)))?;
-    let (input, stanza) = age_stanza(input)?;
-    if stanza.tag != "commitments" {
-        return Err(nom::Err::Failure(Error::new(input, ErrorKind::Tag)));
+    Ok((input, threshold))
+}
+
+/// The `threshold` and `commitments` stanzas that start every header.
+fn threshold_commitments(input: &[u8]) -> IResult<&[u8], (usize, Vec<RistrettoPoint>)> {
+    let (input, (threshold, commitments)) = threshold_any_commitments(input)?;
+    match commitments {
+        Commitments::Pedersen(commitments) => Ok((input, (threshold, commitments))),
+        Commitments::Hash(_) => Err(nom::Err::Failure(Error::new(input, ErrorKind::Tag))),
     }
-    let mut commitments = vec![];
-    for arg in stanza.args {
-        let c = CompressedRistretto::from_slice(
-            base64decode(arg)
-                .map_err(|_| nom::Err::Error(Error::new(input, ErrorKind::Satisfy)))?
-                .as_slice(),
-        )
-        .map_err(|_| nom::Err::Error(Error::new(input, ErrorKind::Satisfy)))?
+}
+
+/// The `threshold` stanza, then either `commitments` or `hash-commitments`.
+fn threshold_any_commitments(input: &[u8]) -> IResult<&[u8], (usize, Commitments)> {
+    let (input, threshold) = threshold(input)?;
+    let (input, stanza) = age_stanza(input)?;
+    let invalid = || nom::Err::Error(Error::new(input, ErrorKind::Satisfy));
+    let commitments = match stanza.tag {
+        "commitments" => {
+            let mut commitments = vec![];
+            for arg in stanza.args {
+                commitments.push(point(arg).ok_or_else(invalid)?);
+            }
+            Commitments::Pedersen(commitments)
+        }
+        "hash-commitments" => {
+            if stanza.args.len() != threshold {
+                return Err(invalid());
+            }
+            let mut masked = vec![];
+            for arg in &stanza.args {
+                masked.push(scalar(arg).ok_or_else(invalid)?);
+            }
+            let body = stanza.body();
+            if body.is_empty() || !body.len().is_multiple_of(32) {
+                return Err(invalid());
+            }
+            let digests = body.chunks(32).map(|d| d.try_into().unwrap()).collect();
+            Commitments::Hash(HashCommitments { masked, digests })
+        }
+        _ => return Err(nom::Err::Failure(Error::new(input, ErrorKind::Tag))),
+    };
+    Ok((input, (threshold, commitments)))
+}
+
+fn scalar(arg: &str) -> Option<Scalar> {
+    let bytes = base64decode(arg).ok()?.try_into().ok()?;
+    Scalar::from_canonical_bytes(bytes).into()
+}
+
+fn point(arg: &str) -> Option<RistrettoPoint> {
+    CompressedRistretto::from_slice(base64decode(arg).ok()?.as_slice())
+        .ok()?
         .decompress()
-        .ok_or(nom::Err::Error(Error::new(input, ErrorKind::Satisfy)))?;
-        commitments.push(c);
-    }
+}
+
+pub fn header(input: &[u8]) -> IResult<&[u8], Header> {
+    let (input, version) = version_line(input)?;
+    let (input, (threshold, commitments)) = threshold_any_commitments(input)?;
     let (input, (mut stanzas, _)) = many_till(age_stanza, hmac_line)(input)?;
+    let mut extensions = vec![];
     let mut current_share = None;
     let mut enc_shares = vec![];
     for s in stanzas.drain(..) {
-        if s.tag == "share" {
+        let extension = s.tag == "ext" || s.tag == "crit";
+        if extension && version >= HeaderVersion::V1 && current_share.is_none() {
+            let Some((name, args)) = s.args.split_first() else {
+                return Err(nom::Err::Error(Error::new(input, ErrorKind::Satisfy)));
+            };
+            extensions.push(Extension {
+                critical: s.tag == "crit",
+                name: name.to_string(),
+                args: args.iter().map(|a| a.to_string()).collect(),
+                body: s.body(),
+            });
+        } else if s.tag == "share-v1" {
+            if let Some(share) = current_share {
+                enc_shares.push(share);
+            }
+            current_share = Some(
+                share_v1(&s.args).ok_or(nom::Err::Error(Error::new(input, ErrorKind::Satisfy)))?,
+            );
+        } else if s.tag == "share" {
+            // Unauthenticated legacy shares would let a newer header be downgraded.
+            if version >= HeaderVersion::V1 {
+                return Err(nom::Err::Error(Error::new(input, ErrorKind::Satisfy)));
+            }
             if let Some(share) = current_share {
                 enc_shares.push(share);
             }
@@ -72,6 +153,7 @@ pub fn header(input: &[u8]) -> IResult<&[u8], Header> {
                     .map_err(|_| nom::Err::Error(Error::new(input, ErrorKind::Satisfy)))?
                     .try_into()
                     .map_err(|_| nom::Err::Error(Error::new(input, ErrorKind::Satisfy)))?,
+                tag: None,
                 stanzas: vec![],
             });
         } else {
@@ -85,9 +167,102 @@ pub fn header(input: &[u8]) -> IResult<&[u8], Header> {
     Ok((
         input,
         Header {
+            version,
             threshold,
             commitments,
+            extensions,
             enc_shares,
         },
     ))
 }
+
+/// The arguments of a `share-v1` stanza: the index, then both halves of the ciphertext and the tag.
+fn share_v1(args: &[&str]) -> Option<EncShare> {
+    if args.len() != 4 {
+        return None;
+    }
+    Some(EncShare {
+        index: args[0].parse().ok()?,
+        s: base64decode(args[1]).ok()?.try_into().ok()?,
+        t: base64decode(args[2]).ok()?.try_into().ok()?,
+        tag: Some(base64decode(args[3]).ok()?.try_into().ok()?),
+        stanzas: vec![],
+    })
+}
+
+fn age_mac_line(input: &[u8]) -> IResult<&[u8], [u8; 32]> {
+    let (input, _) = tag(AGE_MAC_PREFIX)(input)?;
+    let (input, _) = tag(b" ")(input)?;
+    let (input, mac) =
+        take_while1(|c: u8| c.is_ascii_alphanumeric() || c == b'+' || c == b'/')(input)?;
+    let (input, _) = tag(b"\n")(input)?;
+    let mac = STANDARD_NO_PAD
+        .decode(mac)
+        .ok()
+        .and_then(|mac| mac.try_into().ok())
+        .ok_or(nom::Err::Failure(Error::new(input, ErrorKind::Satisfy)))?;
+    Ok((input, mac))
+}
+
+/// Parse the header of a file with publicly verifiable shares.
+pub fn pvss_header(input: &[u8]) -> IResult<&[u8], PvssHeader> {
+    let (input, _) = tag(PVSS_VERSION_LINE)(input)?;
+    let (input, (threshold, commitments)) = threshold_commitments(input)?;
+    let (input, (stanzas, _)) = many_till(age_stanza, hmac_line)(input)?;
+    let mut shares = vec![];
+    for s in stanzas {
+        let invalid = || nom::Err::Error(Error::new(input, ErrorKind::Satisfy));
+        if s.tag != "pvss-share" || s.args.len() != 4 || !s.body().is_empty() {
+            return Err(invalid());
+        }
+        shares.push(PvssShare {
+            index: s.args[0].parse().map_err(|_| invalid())?,
+            recipient: RistrettoRecipient(point(s.args[1]).ok_or_else(invalid)?),
+            encrypted: point(s.args[2]).ok_or_else(invalid)?,
+            proof: DleqProof::from_bytes(&base64decode(s.args[3]).map_err(|_| invalid())?)
+                .map_err(|_| invalid())?,
+        });
+    }
+    Ok((
+        input,
+        PvssHeader {
+            threshold,
+            commitments,
+            shares,
+        },
+    ))
+}
+
+/// Parse the header of a file encrypted to a committee key.
+pub fn committee_header(input: &[u8]) -> IResult<&[u8], CommitteeHeader> {
+    let (input, _) = tag(COMMITTEE_VERSION_LINE)(input)?;
+    let (input, (threshold, commitments)) = threshold_commitments(input)?;
+    let (input, stanza) = age_stanza(input)?;
+    let invalid = || nom::Err::Error(Error::new(input, ErrorKind::Satisfy));
+    if stanza.tag != "committee" || stanza.args.len() != 1 || !stanza.body().is_empty() {
+        return Err(invalid());
+    }
+    let ephemeral = point(stanza.args[0]).ok_or_else(invalid)?;
+    let (input, _) = hmac_line(input)?;
+    Ok((
+        input,
+        CommitteeHeader {
+            threshold,
+            commitments,
+            ephemeral,
+        },
+    ))
+}
+
+/// Parse the header of a plain age v1 file.
+pub fn age_header(input: &[u8]) -> IResult<&[u8], AgeHeader> {
+    let (input, _) = tag(AGE_VERSION_LINE)(input)?;
+    let (input, (stanzas, mac)) = many_till(age_stanza, age_mac_line)(input)?;
+    Ok((
+        input,
+        AgeHeader {
+            stanzas: stanzas.into_iter().map(|s| s.into()).collect(),
+            mac,
+        },
+    ))
+}
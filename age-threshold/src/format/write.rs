@@ -1,23 +1,35 @@
 use cookie_factory::combinator::slice;
 use cookie_factory::{GenResult, WriteContext};
 
-use base64::{engine::general_purpose::STANDARD, Engine as _};
+use base64::{
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD},
+    Engine as _,
+};
 
 use age_core::format::write::age_stanza;
+use age_core::format::Stanza;
 
 use std::io::Write;
 
 use curve25519_dalek::RistrettoPoint;
 
-use crate::format::common::VERSION_LINE;
-use crate::types::EncShare;
+use crate::format::common::{
+    AGE_MAC_PREFIX, AGE_VERSION_LINE, COMMITTEE_VERSION_LINE, PVSS_VERSION_LINE, VERSION_LINE_V0,
+    VERSION_LINE_V1,
+};
+use crate::types::{
+    AgeHeader, Commitments, CommitteeHeader, Extension, Header, HeaderVersion, PvssHeader,
+};
 
 fn base64encode(data: &[u8]) -> String {
     STANDARD.encode(data)
 }
 
-fn version_line<W: Write>(wc: WriteContext<W>) -> GenResult<W> {
-    slice(VERSION_LINE)(wc)
+fn version_line<W: Write>(version: HeaderVersion) -> impl Fn(WriteContext<W>) -> GenResult<W> {
+    move |wc| match version {
+        HeaderVersion::V0 => slice(VERSION_LINE_V0)(wc),
+        HeaderVersion::V1 => slice(VERSION_LINE_V1)(wc),
+    }
 }
 
 fn hmac_line<W: Write>(wc: WriteContext<W>) -> GenResult<W> {
@@ -25,34 +37,132 @@ fn hmac_line<W: Write>(wc: WriteContext<W>) -> GenResult<W> {
     slice("---\n")(wc)
 }
 
-pub fn header<'a, W: Write>(
-    t: usize,
+pub fn header<'a, W: Write>(header: &'a Header) -> impl Fn(WriteContext<W>) -> GenResult<W> + 'a {
+    move |mut wc| {
+        wc = version_line(header.version)(wc)?;
+        wc = age_stanza("threshold", &[&header.threshold.to_string()], &[])(wc)?;
+        wc = match &header.commitments {
+            Commitments::Pedersen(commitments) => pedersen_commitments(commitments)(wc)?,
+            Commitments::Hash(commitments) => {
+                let args: Vec<_> = commitments
+                    .masked
+                    .iter()
+                    .map(|m| base64encode(m.as_bytes()))
+                    .collect();
+                let body = commitments.digests.concat();
+                wc = age_stanza("hash-commitments", &args[..], &body)(wc)?;
+                wc
+            }
+        };
+        for e in &header.extensions {
+            wc = extension(e)(wc)?;
+        }
+        for es in &header.enc_shares {
+            let mut args = vec![
+                es.index.to_string(),
+                base64encode(&es.s),
+                base64encode(&es.t),
+            ];
+            let tag = match &es.tag {
+                Some(tag) => {
+                    args.push(base64encode(tag));
+                    "share-v1"
+                }
+                None => "share",
+            };
+            wc = age_stanza(tag, &args, &[])(wc)?;
+            for s in &es.stanzas {
+                wc = age_stanza(&s.tag, &s.args, &s.body)(wc)?;
+            }
+        }
+        wc = hmac_line(wc)?;
+        Ok(wc)
+    }
+}
+
+pub fn extension<'a, W: Write>(e: &'a Extension) -> impl Fn(WriteContext<W>) -> GenResult<W> + 'a {
+    move |wc| {
+        let mut args = vec![e.name.clone()];
+        args.extend(e.args.iter().cloned());
+        let tag = if e.critical { "crit" } else { "ext" };
+        let wc = age_stanza(tag, &args, &e.body)(wc)?;
+        Ok(wc)
+    }
+}
+
+fn pedersen_commitments<'a, W: Write>(
     commitments: &'a [RistrettoPoint],
-    enc_shares: &'a [EncShare],
 ) -> impl Fn(WriteContext<W>) -> GenResult<W> + 'a {
     move |mut wc| {
-        wc = version_line(wc)?;
-        wc = age_stanza("threshold", &[&t.to_string()], &[])(wc)?;
         let args: Vec<_> = commitments
             .iter()
-            .map(|c| STANDARD.encode(c.compress().as_bytes()))
+            .map(|c| base64encode(c.compress().as_bytes()))
             .collect();
         wc = age_stanza("commitments", &args[..], &[])(wc)?;
-        for es in enc_shares {
+        Ok(wc)
+    }
+}
+
+pub fn pvss_header<'a, W: Write>(
+    header: &'a PvssHeader,
+) -> impl Fn(WriteContext<W>) -> GenResult<W> + 'a {
+    move |mut wc| {
+        wc = slice(PVSS_VERSION_LINE)(wc)?;
+        wc = age_stanza("threshold", &[&header.threshold.to_string()], &[])(wc)?;
+        wc = pedersen_commitments(&header.commitments)(wc)?;
+        for s in &header.shares {
             wc = age_stanza(
-                "share",
+                "pvss-share",
                 &[
-                    es.index.to_string(),
-                    base64encode(&es.s),
-                    base64encode(&es.t),
+                    s.index.to_string(),
+                    base64encode(s.recipient.0.compress().as_bytes()),
+                    base64encode(s.encrypted.compress().as_bytes()),
+                    base64encode(&s.proof.to_bytes()),
                 ],
                 &[],
             )(wc)?;
-            for s in &es.stanzas {
-                wc = age_stanza(&s.tag, &s.args, &s.body)(wc)?;
-            }
         }
         wc = hmac_line(wc)?;
         Ok(wc)
     }
 }
+
+pub fn committee_header<'a, W: Write>(
+    header: &'a CommitteeHeader,
+) -> impl Fn(WriteContext<W>) -> GenResult<W> + 'a {
+    move |mut wc| {
+        wc = slice(COMMITTEE_VERSION_LINE)(wc)?;
+        wc = age_stanza("threshold", &[&header.threshold.to_string()], &[])(wc)?;
+        wc = pedersen_commitments(&header.commitments)(wc)?;
+        let ephemeral = base64encode(header.ephemeral.compress().as_bytes());
+        wc = age_stanza("committee", &[ephemeral], &[])(wc)?;
+        wc = hmac_line(wc)?;
+        Ok(wc)
+    }
+}
+
+/// The part of a plain age v1 header that its MAC is computed over.
+pub fn age_header_mac_input<'a, W: Write>(
+    stanzas: &'a [Stanza],
+) -> impl Fn(WriteContext<W>) -> GenResult<W> + 'a {
+    move |mut wc| {
+        wc = slice(AGE_VERSION_LINE)(wc)?;
+        for s in stanzas {
+            wc = age_stanza(&s.tag, &s.args, &s.body)(wc)?;
+        }
+        wc = slice(AGE_MAC_PREFIX)(wc)?;
+        Ok(wc)
+    }
+}
+
+pub fn age_header<'a, W: Write>(
+    header: &'a AgeHeader,
+) -> impl Fn(WriteContext<W>) -> GenResult<W> + 'a {
+    move |mut wc| {
+        wc = age_header_mac_input(&header.stanzas)(wc)?;
+        wc = slice(" ")(wc)?;
+        wc = slice(STANDARD_NO_PAD.encode(header.mac))(wc)?;
+        wc = slice("\n")(wc)?;
+        Ok(wc)
+    }
+}
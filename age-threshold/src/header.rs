@@ -0,0 +1,153 @@
+//! Reading, writing and building headers.
+
+use age_core::format::FileKey;
+use nom_bufreader::bufreader::BufReader;
+use nom_bufreader::Parse;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::io;
+use std::io::prelude::*;
+
+use crate::metadata::seal_metadata;
+use crate::shares::{encrypt_shares, key_commitment};
+use crate::{crypto, format, types};
+
+/// Names of the critical header extensions this version understands.
+const CRITICAL_EXTENSIONS: &[&str] = &[types::KEY_COMMITMENT_EXTENSION, types::PADDING_EXTENSION];
+
+/// Own the errors of `parser`, so they can outlive the reader's buffer.
+fn owned<T>(
+    parser: fn(&[u8]) -> nom::IResult<&[u8], T>,
+) -> impl for<'a> Fn(&'a [u8]) -> nom::IResult<&'a [u8], T, nom::error::Error<Vec<u8>>> {
+    move |input| parser(input).map_err(|err| err.to_owned())
+}
+
+/// Parse one header with `parser`, mapping parse failures to `InvalidData`.
+fn parse<T>(
+    input: &mut BufReader<impl Read>,
+    parser: fn(&[u8]) -> nom::IResult<&[u8], T>,
+) -> io::Result<T> {
+    input.parse(owned(parser)).map_err(|err| match err {
+        nom_bufreader::Error::Error(_) | nom_bufreader::Error::Failure(_) => {
+            io::Error::new(io::ErrorKind::InvalidData, "parse error")
+        }
+        nom_bufreader::Error::Io(err) => err,
+        nom_bufreader::Error::Eof => io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected eof"),
+    })
+}
+
+pub(crate) fn read_header(input: &mut BufReader<impl Read>) -> io::Result<types::Header> {
+    let header = parse(input, format::read::header)?;
+    if let Some(e) = header
+        .extensions
+        .iter()
+        .find(|e| e.critical && !CRITICAL_EXTENSIONS.contains(&e.name.as_str()))
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported critical extension {}", e.name),
+        ));
+    }
+    Ok(header)
+}
+
+pub(crate) fn read_pvss_header(input: &mut BufReader<impl Read>) -> io::Result<types::PvssHeader> {
+    parse(input, format::read::pvss_header)
+}
+
+pub(crate) fn read_committee_header(
+    input: &mut BufReader<impl Read>,
+) -> io::Result<types::CommitteeHeader> {
+    let header = parse(input, format::read::committee_header)?;
+    if header.threshold == 0 || header.commitments.len() != header.threshold {
+        return Err(io::Error::other("invalid threshold"));
+    }
+    Ok(header)
+}
+
+pub(crate) fn read_age_header(input: &mut BufReader<impl Read>) -> io::Result<types::AgeHeader> {
+    parse(input, format::read::age_header)
+}
+
+pub(crate) fn new_header(
+    file_key: &FileKey,
+    recipients: &[types::AgeRecipient],
+    t: u32,
+    opts: &types::EncryptOptions,
+) -> io::Result<types::Header> {
+    let n = recipients.len() as u32;
+    if n < t {
+        return Err(io::Error::other("not enough recipients"));
+    }
+
+    if opts.version < types::HeaderVersion::V1 && opts.scheme == types::CommitmentScheme::Hash {
+        return Err(io::Error::other(
+            "hash commitments need a newer header version",
+        ));
+    }
+    let (shares, commitments) = match opts.scheme {
+        types::CommitmentScheme::Pedersen => {
+            let (shares, c) = crypto::share_secret(file_key, t, n);
+            (shares, types::Commitments::Pedersen(c))
+        }
+        types::CommitmentScheme::Hash => {
+            let (shares, c) = crypto::hash_vss::share_secret(file_key, t, n);
+            (shares, types::Commitments::Hash(c))
+        }
+    };
+    let mut header = types::Header {
+        version: opts.version,
+        threshold: t as usize,
+        commitments,
+        extensions: vec![],
+        enc_shares: vec![],
+    };
+    for (share, label) in shares.iter().zip(&opts.labels) {
+        if let Some(label) = label.as_ref().filter(|l| !l.is_empty()) {
+            header.extensions.push(types::Extension {
+                critical: false,
+                name: types::LABEL_EXTENSION.to_string(),
+                args: vec![share.index.to_string()],
+                body: label.as_bytes().to_vec(),
+            });
+        }
+    }
+    if opts.padding.is_some() {
+        header.extensions.push(types::Extension {
+            critical: true,
+            name: types::PADDING_EXTENSION.to_string(),
+            args: vec![],
+            body: vec![],
+        });
+    }
+    // Commit the payload to the file key, so that every quorum decrypts the same plaintext.
+    if opts.version >= types::HeaderVersion::V1 {
+        header.extensions.push(types::Extension {
+            critical: true,
+            name: types::KEY_COMMITMENT_EXTENSION.to_string(),
+            args: vec![],
+            body: key_commitment(file_key).to_vec(),
+        });
+    }
+    if let Some(metadata) = &opts.metadata {
+        let e = seal_metadata(metadata, file_key, &header)?;
+        header.extensions.push(e);
+    }
+    header.enc_shares = encrypt_shares(&shares, recipients, &header)?;
+    Ok(header)
+}
+
+pub(crate) fn write_header<W: Write>(output: W, header: &types::Header) -> io::Result<W> {
+    if header.version < types::HeaderVersion::V1 && !header.extensions.is_empty() {
+        return Err(io::Error::other("extensions need a newer header version"));
+    }
+    let (output, _) =
+        cookie_factory::gen(format::write::header(header), output).map_err(io::Error::other)?;
+    Ok(output)
+}
+
+pub(crate) fn new_file_key() -> FileKey {
+    let mut buf = [0; 16];
+    OsRng.fill_bytes(&mut buf);
+    FileKey::from(buf)
+}
@@ -2,30 +2,45 @@ pub mod crypto;
 pub mod format;
 pub mod types;
 
-use age::cli_common::UiCallbacks;
-use age_core::format::FileKey;
-use age_core::secrecy::ExposeSecret;
-
-use chacha20::cipher::{KeyIvInit, StreamCipher};
-use chacha20::ChaCha20;
-use chacha20poly1305::{AeadInPlace, ChaCha20Poly1305, KeyInit};
-
-use curve25519_dalek::scalar::Scalar;
+mod committee;
+mod convert;
+mod enroll;
+mod header;
+mod metadata;
+mod payload;
+mod pvss;
+mod refresh;
+mod request;
+mod session;
+mod shares;
+mod signature;
+mod split;
 
 use nom_bufreader::bufreader::BufReader;
-use nom_bufreader::Parse;
-
+use sha2::{Digest, Sha256};
 use std::io;
 use std::io::prelude::*;
 
-use age_core::primitives::hkdf;
-use rand::rngs::OsRng;
-use rand::RngCore;
-
-const PAYLOAD_KEY_LABEL: &[u8] = b"payload";
-const NONCE_SIZE: usize = 16;
-const CHUNK_SIZE: usize = 64 * 1024;
-const TAG_SIZE: usize = 16;
+pub use committee::{
+    committee_deal, committee_finish, committee_reveal, decrypt_committee, encrypt_committee,
+    partial_decrypt, read_deal,
+};
+pub use convert::{from_age, to_age, unwrap_file_key, wrap_file_key};
+pub use enroll::{enroll, open_piece, seal_piece};
+pub use metadata::{decrypt_with_metadata, read_metadata};
+pub use pvss::{decrypt_pvss, encrypt_pvss, verify_pvss};
+pub use refresh::{refresh, rewrap};
+pub use request::{
+    decrypt_with_requests, fulfill, read_request, request, request_shares, respond, respond_to,
+};
+pub use session::{collect_shares, read_session, write_session};
+pub use signature::{decrypt_signed, signer};
+pub use split::{combine, combine_identity, split, split_identity};
+
+use header::{new_file_key, new_header, read_header, write_header};
+use payload::{decrypt_payload, encrypt_payload_with};
+use shares::{share_files, unlock_shares, verify_share};
+use signature::{sign_header, signature, HashingWriter};
 
 pub fn encrypt(
     recipients: &[types::AgeRecipient],
@@ -33,58 +48,64 @@ pub fn encrypt(
     input: &mut impl Read,
     output: &mut impl Write,
 ) -> io::Result<()> {
-    let file_key = new_file_key();
-    let n = recipients.len() as u32;
-
-    if n < t {
-        return Err(io::Error::other("not enough recipients"));
-    }
-
-    let (shares, commitments) = crypto::share_secret(&file_key, t, n);
-    let mut enc_shares = vec![];
-    for (r, share) in recipients.iter().zip(shares.iter()) {
-        let recipient = r.to_recipient(UiCallbacks).map_err(io::Error::other)?;
-        let share_key = new_file_key();
-        let mut cipher = ChaCha20::new(
-            &hkdf(&[], b"", &share_key.expose_secret()[..]).into(),
-            (&[0; 12]).into(),
-        );
-        let mut s = share.s.to_bytes();
-        cipher.apply_keystream(&mut s);
-        let mut t = share.t.to_bytes();
-        cipher.apply_keystream(&mut t);
-        let shares = recipient
-            .wrap_file_key(&share_key)
-            .map_err(io::Error::other)?;
-        enc_shares.push(types::EncShare {
-            index: share.index,
-            s,
-            t,
-            stanzas: shares,
-        });
-    }
-
-    let (output, _) = cookie_factory::gen(
-        format::write::header(t as usize, &commitments, &enc_shares),
+    encrypt_with(
+        recipients,
+        t,
+        &types::EncryptOptions::default(),
+        input,
         output,
     )
-    .map_err(io::Error::other)?;
-
-    let mut nonce = [0; NONCE_SIZE];
-    OsRng.fill_bytes(&mut nonce);
-    let payload_key = &hkdf(nonce.as_ref(), PAYLOAD_KEY_LABEL, file_key.expose_secret());
-    output.write_all(&nonce)?;
+}
 
-    let aead = ChaCha20Poly1305::new(payload_key.into());
-    let input = BufReader::new(input);
-    for_chunks(input, CHUNK_SIZE, |counter, last_chunk, buf| {
-        let mut iv = [0; 12];
-        iv[..11].copy_from_slice(&counter.to_le_bytes()[..11]);
-        iv[11] = last_chunk as u8;
-        aead.encrypt_in_place((&iv).into(), b"", buf)
-            .map_err(io::Error::other)?;
-        output.write_all(buf)
-    })
+/// Like [`encrypt`], with the given options.
+///
+/// With [`types::CommitmentScheme::Hash`], share verification assumes nothing about discrete
+/// logarithms, but share files, enrollment and refreshing are not available. Pinning
+/// [`types::HeaderVersion::V0`] writes the file as three 0.1.0 did, down to the little-endian
+/// chunk counter of the payload, so that readers that predate version 1 can decrypt it.
+///
+/// With a signer, the payload is first written to a temporary file, since its digest is
+/// signed along with the header that precedes it.
+pub fn encrypt_with(
+    recipients: &[types::AgeRecipient],
+    t: u32,
+    opts: &types::EncryptOptions,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let file_key = new_file_key();
+    let mut header = new_header(&file_key, recipients, t, opts)?;
+    let Some(signer) = &opts.signer else {
+        let output = write_header(output, &header)?;
+        return encrypt_payload_with(
+            &file_key,
+            opts.version,
+            opts.padding.as_ref(),
+            input,
+            output,
+        );
+    };
+    let mut payload = HashingWriter {
+        inner: tempfile::tempfile()?,
+        hasher: Sha256::new(),
+    };
+    encrypt_payload_with(
+        &file_key,
+        opts.version,
+        opts.padding.as_ref(),
+        input,
+        &mut payload,
+    )?;
+    header.extensions.push(sign_header(
+        &header,
+        signer,
+        &payload.hasher.finalize().into(),
+    ));
+    let output = write_header(output, &header)?;
+    let mut payload = payload.inner;
+    payload.rewind()?;
+    io::copy(&mut payload, output)?;
+    Ok(())
 }
 
 pub fn decrypt(
@@ -93,115 +114,85 @@ pub fn decrypt(
     output: &mut impl Write,
 ) -> io::Result<()> {
     let mut input = BufReader::new(input);
-    fn header(input: &[u8]) -> nom::IResult<&[u8], types::Header, nom::error::Error<Vec<u8>>> {
-        format::read::header(input).map_err(|err| err.to_owned())
-    }
-    let header = input.parse(header).map_err(|err| match err {
-        nom_bufreader::Error::Error(_) => io::Error::new(io::ErrorKind::InvalidData, "parse error"),
-        nom_bufreader::Error::Failure(_) => {
-            io::Error::new(io::ErrorKind::InvalidData, "parse error")
-        }
-        nom_bufreader::Error::Io(err) => err,
-        nom_bufreader::Error::Eof => io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected eof"),
-    })?;
+    let header = read_header(&mut input)?;
 
-    let mut shares = vec![];
-    for es in header.enc_shares {
-        if shares.len() >= header.threshold {
-            break;
-        }
+    let shares = unlock_shares(identities, &header)?;
+    decrypt_payload(&header, &shares, input, output)
+}
 
-        if let Some(share_key) = decrypt_fk(identities, &es)? {
-            let mut cipher = ChaCha20::new(
-                &hkdf(&[], b"", &share_key.expose_secret()[..]).into(),
-                (&[0; 12]).into(),
-            );
-            let mut s = es.s;
-            cipher.apply_keystream(&mut s);
-            let mut t = es.t;
-            cipher.apply_keystream(&mut t);
-            let s = Scalar::from_bytes_mod_order(s);
-            let t = Scalar::from_bytes_mod_order(t);
-            let share = types::SecretShare {
-                index: es.index,
-                s,
-                t,
-            };
-            if !crypto::verify_share(&share, &header.commitments) {
-                return Err(io::Error::other("invalid share"));
-            }
-            shares.push(share);
-        }
-    }
-    if shares.len() < header.threshold {
-        return Err(io::Error::other("not enough shares"));
-    }
-    let file_key = crypto::reconstruct_secret(&shares);
+/// Parse the header of `input` without unwrapping anything, to show what it holds.
+///
+/// Labels are not authenticated until the share they label is decrypted.
+pub fn inspect(input: &mut impl Read) -> io::Result<types::Header> {
+    read_header(&mut BufReader::new(input))
+}
 
-    let mut nonce = [0; NONCE_SIZE];
-    input.read_exact(&mut nonce)?;
-    let payload_key = &hkdf(nonce.as_ref(), PAYLOAD_KEY_LABEL, file_key.expose_secret());
-    let aead = ChaCha20Poly1305::new(payload_key.into());
+/// Unwrap and verify the shares of a file that `identities` have access to.
+pub fn decrypt_shares(
+    identities: &[types::AgeIdentity],
+    input: &mut impl Read,
+) -> io::Result<Vec<types::SecretShare>> {
+    let header = read_header(&mut BufReader::new(input))?;
+    unlock_shares(identities, &header)
+}
 
-    for_chunks(input, CHUNK_SIZE + TAG_SIZE, |counter, last_chunk, buf| {
-        let mut iv = [0; 12];
-        iv[..11].copy_from_slice(&counter.to_le_bytes()[..11]);
-        iv[11] = last_chunk as u8;
-        aead.decrypt_in_place((&iv).into(), b"", buf)
-            .map_err(io::Error::other)?;
-        output.write_all(buf)
-    })
+/// Decrypt one's own shares like [`decrypt_shares`], as self-describing share files.
+pub fn export_shares(
+    identities: &[types::AgeIdentity],
+    input: &mut impl Read,
+) -> io::Result<Vec<types::ShareFile>> {
+    export_header_shares(identities, &read_header(&mut BufReader::new(input))?)
 }
 
-fn new_file_key() -> FileKey {
-    let mut buf = [0; 16];
-    OsRng.fill_bytes(&mut buf);
-    FileKey::from(buf)
+/// Like [`export_shares`], for a header from [`inspect`] that has already been reviewed.
+///
+/// Shares of a header with a bad signature are not released.
+pub fn export_header_shares(
+    identities: &[types::AgeIdentity],
+    header: &types::Header,
+) -> io::Result<Vec<types::ShareFile>> {
+    signature(header)?;
+    share_files(header, unlock_shares(identities, header)?)
 }
 
-fn for_chunks(
-    mut input: BufReader<impl Read>,
-    chunk_size: usize,
-    mut f: impl FnMut(u128, bool, &mut Vec<u8>) -> io::Result<()>,
+/// Decrypt `input` with the shares of a session filled by [`collect_shares`].
+pub fn decrypt_with_shares(
+    session: &[types::ShareFile],
+    input: &mut impl Read,
+    output: &mut impl Write,
 ) -> io::Result<()> {
-    let mut buf = vec![0; chunk_size];
-    let mut counter = 0u128;
-    loop {
-        let mut n = 0;
-        while n < buf.len() {
-            match input.read(&mut buf[n..]) {
-                Ok(0) => break,
-                Ok(m) => n += m,
-                Err(err) => return Err(err),
-            }
-        }
-        if n < buf.len() || input.fill_buf()?.is_empty() {
-            buf.truncate(n);
-            f(counter, true, &mut buf)?;
-            break;
-        }
-        f(counter, false, &mut buf)?;
-        counter += 1;
+    let mut input = BufReader::new(input);
+    let header = read_header(&mut input)?;
+    let fingerprint = header.fingerprint();
+    if session.iter().any(|f| f.fingerprint != fingerprint) {
+        return Err(io::Error::other("session belongs to another file"));
     }
-    Ok(())
+    let shares: Vec<_> = session.iter().map(|f| f.share.clone()).collect();
+    decrypt_payload(&header, &shares, input, output)
 }
 
-fn decrypt_fk(
-    identities: &[types::AgeIdentity],
-    es: &types::EncShare,
-) -> io::Result<Option<FileKey>> {
-    for identity in identities {
-        for s in &es.stanzas {
-            match identity
-                .to_identity(UiCallbacks)
-                .map_err(io::Error::other)?
-                .unwrap_stanza(s)
-            {
-                Some(Ok(file_key)) => return Ok(Some(file_key)),
-                Some(Err(err)) => return Err(io::Error::other(err)),
-                None => continue,
-            }
+/// Decrypt `input` with shares exported by their holders, as share files or as words.
+///
+/// Words do not name the file they belong to, so every share is verified against the
+/// commitments of the header instead.
+pub fn decrypt_with_exported_shares(
+    exported: &[types::SecretShare],
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut input = BufReader::new(input);
+    let header = read_header(&mut input)?;
+    let mut shares: Vec<types::SecretShare> = vec![];
+    for share in exported {
+        if !verify_share(&header, share) {
+            return Err(io::Error::other(format!(
+                "share {} does not belong to this file",
+                share.index
+            )));
+        }
+        if !shares.iter().any(|s| s.index == share.index) {
+            shares.push(share.clone());
         }
     }
-    Ok(None)
+    decrypt_payload(&header, &shares, input, output)
 }
@@ -0,0 +1,86 @@
+//! The encrypted metadata extension.
+
+use age_core::format::FileKey;
+use age_core::primitives::hkdf;
+use age_core::secrecy::ExposeSecret;
+use chacha20poly1305::{AeadInPlace, ChaCha20Poly1305, KeyInit};
+use nom_bufreader::bufreader::BufReader;
+use std::io;
+use std::io::prelude::*;
+
+use crate::header::read_header;
+use crate::shares::{reconstruct_file_key, unlock_shares};
+use crate::signature::decrypt_signed_stream;
+use crate::types;
+
+const METADATA_KEY_LABEL: &[u8] = b"age-threshold metadata";
+
+/// Like [`decrypt`](crate::decrypt), opening the output with the metadata of `input`, if any, once a quorum
+/// has unlocked it.
+pub fn decrypt_with_metadata<W: Write>(
+    identities: &[types::AgeIdentity],
+    input: &mut impl Read,
+    output: impl FnOnce(Option<&types::Metadata>) -> io::Result<W>,
+) -> io::Result<()> {
+    let mut input = BufReader::new(input);
+    let header = read_header(&mut input)?;
+
+    let shares = unlock_shares(identities, &header)?;
+    let file_key = reconstruct_file_key(&header, &shares)?;
+    let metadata = open_metadata(&header, &file_key)?;
+    let mut output = output(metadata.as_ref())?;
+    decrypt_signed_stream(&header, &file_key, input, &mut output)?;
+    output.flush()
+}
+
+/// Decrypt the metadata of a header from [`inspect`](crate::inspect), without touching the payload.
+pub fn read_metadata(
+    identities: &[types::AgeIdentity],
+    header: &types::Header,
+) -> io::Result<Option<types::Metadata>> {
+    let shares = unlock_shares(identities, header)?;
+    open_metadata(header, &reconstruct_file_key(header, &shares)?)
+}
+
+fn metadata_aead(file_key: &FileKey) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(&hkdf(&[], METADATA_KEY_LABEL, file_key.expose_secret()).into())
+}
+
+/// Encrypt `metadata` into an extension. The key is only used once, since the file key is fresh.
+pub(crate) fn seal_metadata(
+    metadata: &types::Metadata,
+    file_key: &FileKey,
+    header: &types::Header,
+) -> io::Result<types::Extension> {
+    let mut body = metadata.to_json().into_bytes();
+    metadata_aead(file_key)
+        .encrypt_in_place(&[0; 12].into(), &header.share_context(), &mut body)
+        .map_err(io::Error::other)?;
+    Ok(types::Extension {
+        critical: false,
+        name: types::METADATA_EXTENSION.to_string(),
+        args: vec![],
+        body,
+    })
+}
+
+fn open_metadata(
+    header: &types::Header,
+    file_key: &FileKey,
+) -> io::Result<Option<types::Metadata>> {
+    let Some(e) = header
+        .extensions
+        .iter()
+        .find(|e| e.name == types::METADATA_EXTENSION)
+    else {
+        return Ok(None);
+    };
+    let mut buf = e.body.clone();
+    metadata_aead(file_key)
+        .decrypt_in_place(&[0; 12].into(), &header.share_context(), &mut buf)
+        .map_err(|_| io::Error::other("metadata decryption failed"))?;
+    let json = String::from_utf8(buf).map_err(io::Error::other)?;
+    types::Metadata::from_json(&json)
+        .map(Some)
+        .map_err(io::Error::other)
+}
@@ -0,0 +1,337 @@
+//! The payload: STREAM encryption of the plaintext, optionally padded.
+
+use age_core::format::FileKey;
+use age_core::primitives::hkdf;
+use age_core::secrecy::ExposeSecret;
+use chacha20poly1305::{AeadInPlace, ChaCha20Poly1305, KeyInit};
+use nom_bufreader::bufreader::BufReader;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::io;
+use std::io::prelude::*;
+
+use crate::shares::reconstruct_file_key;
+use crate::signature::decrypt_signed_stream;
+use crate::types;
+
+const PAYLOAD_KEY_LABEL: &[u8] = b"payload";
+
+const PADDED_PAYLOAD_KEY_LABEL: &[u8] = b"age-threshold padded payload";
+
+const PADDING_MARKER: u8 = 0x80;
+
+const NONCE_SIZE: usize = 16;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+const TAG_SIZE: usize = 16;
+
+/// Encrypt a PVSS or committee payload, which counts chunks little-endian.
+pub(crate) fn encrypt_payload(
+    file_key: &FileKey,
+    input: &mut impl Read,
+    output: impl Write,
+) -> io::Result<()> {
+    encrypt_chunks(file_key, PAYLOAD_KEY_LABEL, true, input, output)
+}
+
+/// Encrypt the payload of a threshold file under a header of `version`.
+pub(crate) fn encrypt_payload_with(
+    file_key: &FileKey,
+    version: types::HeaderVersion,
+    padding: Option<&types::Padding>,
+    input: &mut impl Read,
+    output: impl Write,
+) -> io::Result<()> {
+    let little_endian = little_endian(version);
+    let Some(padding) = padding else {
+        return encrypt_chunks(file_key, PAYLOAD_KEY_LABEL, little_endian, input, output);
+    };
+    // A key of its own, so that the padding extension cannot be added or removed.
+    encrypt_chunks(
+        file_key,
+        PADDED_PAYLOAD_KEY_LABEL,
+        little_endian,
+        &mut Padder {
+            inner: input,
+            len: 0,
+            padding: *padding,
+            remaining: None,
+        },
+        output,
+    )
+}
+
+/// Whether payloads under a header of `version` count chunks little-endian, as three 0.1.0 did.
+pub(crate) fn little_endian(version: types::HeaderVersion) -> bool {
+    version < types::HeaderVersion::V1
+}
+
+/// A reader that appends the end marker and the padding to a plaintext.
+struct Padder<R> {
+    inner: R,
+    /// Bytes of the input read so far.
+    len: u64,
+    padding: types::Padding,
+    /// Bytes of padding left to read, once the input is over.
+    remaining: Option<u64>,
+}
+
+impl<R: Read> Read for Padder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let Some(remaining) = &mut self.remaining else {
+            let n = self.inner.read(buf)?;
+            if n > 0 {
+                self.len += n as u64;
+                return Ok(n);
+            }
+            // The input is over: the marker, then zeros.
+            self.remaining = Some(self.padding.padded_len(self.len + 1) - self.len - 1);
+            buf[0] = PADDING_MARKER;
+            return Ok(1);
+        };
+        let n = buf
+            .len()
+            .min(usize::try_from(*remaining).unwrap_or(usize::MAX));
+        buf[..n].fill(0);
+        *remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+fn encrypt_chunks(
+    file_key: &FileKey,
+    label: &[u8],
+    little_endian: bool,
+    input: &mut impl Read,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let mut nonce = [0; NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+    let payload_key = &hkdf(nonce.as_ref(), label, file_key.expose_secret());
+    output.write_all(&nonce)?;
+
+    let aead = ChaCha20Poly1305::new(payload_key.into());
+    let input = BufReader::new(input);
+    for_chunks(input, CHUNK_SIZE, |counter, last_chunk, buf| {
+        let iv = stream_nonce(counter, last_chunk, little_endian);
+        aead.encrypt_in_place((&iv).into(), b"", buf)
+            .map_err(io::Error::other)?;
+        output.write_all(buf)
+    })
+}
+
+/// The STREAM nonce of a payload chunk: a big-endian counter as in age,
+/// or the little-endian one of version 0 headers and of PVSS and committee files.
+fn stream_nonce(counter: u128, last_chunk: bool, little_endian: bool) -> [u8; 12] {
+    let mut iv = [0; 12];
+    if little_endian {
+        iv[..11].copy_from_slice(&counter.to_le_bytes()[..11]);
+    } else {
+        iv[..11].copy_from_slice(&counter.to_be_bytes()[5..]);
+    }
+    iv[11] = last_chunk as u8;
+    iv
+}
+
+pub(crate) fn decrypt_payload(
+    header: &types::Header,
+    shares: &[types::SecretShare],
+    input: BufReader<impl Read>,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let file_key = reconstruct_file_key(header, shares)?;
+    decrypt_signed_stream(header, &file_key, input, output)
+}
+
+/// A writer that strips the end marker and the padding of a padded plaintext.
+///
+/// The last `0x80` byte seen and the zeros after it are held back,
+/// until a non-zero byte shows that they were part of the input.
+struct Unpadder<W> {
+    inner: W,
+    /// Whether the held back bytes start with a `0x80` byte.
+    marker: bool,
+    /// Zeros held back, after the marker if there is one.
+    zeros: u64,
+}
+
+impl<W: Write> Unpadder<W> {
+    fn write_held(&mut self) -> io::Result<()> {
+        if std::mem::take(&mut self.marker) {
+            self.inner.write_all(&[PADDING_MARKER])?;
+        }
+        let zeros = [0; 1024];
+        while self.zeros > 0 {
+            let n = self.zeros.min(zeros.len() as u64);
+            self.inner.write_all(&zeros[..n as usize])?;
+            self.zeros -= n;
+        }
+        Ok(())
+    }
+
+    /// Check that the plaintext ended with the padding, which was not written.
+    fn finish(self) -> io::Result<()> {
+        if !self.marker {
+            return Err(invalid_padding());
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Unpadder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(last) = buf.iter().rposition(|&b| b != 0) else {
+            self.zeros += buf.len() as u64;
+            return Ok(buf.len());
+        };
+        self.write_held()?;
+        let held = if buf[last] == PADDING_MARKER {
+            last
+        } else {
+            last + 1
+        };
+        self.inner.write_all(&buf[..held])?;
+        self.marker = held == last;
+        self.zeros = (buf.len() - last - 1) as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn invalid_padding() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid padding")
+}
+
+/// Decrypt the payload of a threshold file, stripping its padding if it has any.
+pub(crate) fn decrypt_padded_stream(
+    header: &types::Header,
+    file_key: &FileKey,
+    input: BufReader<impl Read>,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let little_endian = little_endian(header.version);
+    if !header.padded() {
+        return decrypt_chunks(file_key, PAYLOAD_KEY_LABEL, little_endian, input, output);
+    }
+    let mut output = Unpadder {
+        inner: output,
+        marker: false,
+        zeros: 0,
+    };
+    decrypt_chunks(
+        file_key,
+        PADDED_PAYLOAD_KEY_LABEL,
+        little_endian,
+        input,
+        &mut output,
+    )?;
+    output.finish()
+}
+
+/// Decrypt a PVSS or committee payload, which counts chunks little-endian.
+pub(crate) fn decrypt_stream(
+    file_key: &FileKey,
+    input: BufReader<impl Read>,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    decrypt_chunks(file_key, PAYLOAD_KEY_LABEL, true, input, output)
+}
+
+fn decrypt_chunks(
+    file_key: &FileKey,
+    label: &[u8],
+    little_endian: bool,
+    mut input: BufReader<impl Read>,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut nonce = [0; NONCE_SIZE];
+    input.read_exact(&mut nonce)?;
+    let payload_key = &hkdf(nonce.as_ref(), label, file_key.expose_secret());
+    let aead = ChaCha20Poly1305::new(payload_key.into());
+
+    for_chunks(input, CHUNK_SIZE + TAG_SIZE, |counter, last_chunk, buf| {
+        let iv = stream_nonce(counter, last_chunk, little_endian);
+        aead.decrypt_in_place((&iv).into(), b"", buf)
+            .map_err(|_| chunk_error(counter))?;
+        output.write_all(buf)
+    })
+}
+
+fn chunk_error(counter: u128) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("payload chunk {counter} failed to authenticate"),
+    )
+}
+
+/// Re-encrypt an unpadded payload from one chunk counter encoding to the other,
+/// under a fresh nonce so that no payload key is used with both.
+pub(crate) fn reencrypt_payload(
+    file_key: &FileKey,
+    from_little_endian: bool,
+    mut input: BufReader<impl Read>,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let mut nonce = [0; NONCE_SIZE];
+    input.read_exact(&mut nonce)?;
+    let payload_key = &hkdf(nonce.as_ref(), PAYLOAD_KEY_LABEL, file_key.expose_secret());
+    let from = ChaCha20Poly1305::new(payload_key.into());
+
+    OsRng.fill_bytes(&mut nonce);
+    let payload_key = &hkdf(nonce.as_ref(), PAYLOAD_KEY_LABEL, file_key.expose_secret());
+    let to = ChaCha20Poly1305::new(payload_key.into());
+    output.write_all(&nonce)?;
+
+    for_chunks(input, CHUNK_SIZE + TAG_SIZE, |counter, last_chunk, buf| {
+        let iv = stream_nonce(counter, last_chunk, from_little_endian);
+        from.decrypt_in_place((&iv).into(), b"", buf)
+            .map_err(|_| chunk_error(counter))?;
+        let iv = stream_nonce(counter, last_chunk, !from_little_endian);
+        to.encrypt_in_place((&iv).into(), b"", buf)
+            .map_err(io::Error::other)?;
+        output.write_all(buf)
+    })
+}
+
+fn for_chunks(
+    mut input: BufReader<impl Read>,
+    chunk_size: usize,
+    mut f: impl FnMut(u128, bool, &mut Vec<u8>) -> io::Result<()>,
+) -> io::Result<()> {
+    // Read one byte past the chunk to know whether it is the last one.
+    let mut buf = vec![0; chunk_size + 1];
+    let mut n = read_full(&mut input, &mut buf)?;
+    let mut counter = 0u128;
+    while n > chunk_size {
+        let next = buf[chunk_size];
+        buf.truncate(chunk_size);
+        f(counter, false, &mut buf)?;
+        counter += 1;
+        // `f` works in place and may have resized the chunk.
+        buf.clear();
+        buf.resize(chunk_size + 1, 0);
+        buf[0] = next;
+        n = 1 + read_full(&mut input, &mut buf[1..])?;
+    }
+    buf.truncate(n);
+    f(counter, true, &mut buf)
+}
+
+/// Read until `buf` is full or the input ends, returning the number of bytes read.
+fn read_full(input: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match input.read(&mut buf[n..])? {
+            0 => break,
+            m => n += m,
+        }
+    }
+    Ok(n)
+}
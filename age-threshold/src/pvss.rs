@@ -0,0 +1,84 @@
+//! Files with publicly verifiable shares.
+
+use nom_bufreader::bufreader::BufReader;
+use std::io;
+use std::io::prelude::*;
+
+use crate::header::read_pvss_header;
+use crate::payload::{decrypt_stream, encrypt_payload};
+use crate::{crypto, format, types};
+
+/// Encrypt `input` to ristretto255 `recipients`, any `t` of which can decrypt.
+///
+/// Unlike [`encrypt`](crate::encrypt), the shares are publicly verifiable: see [`verify_pvss`].
+pub fn encrypt_pvss(
+    recipients: &[types::RistrettoRecipient],
+    t: u32,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    if t == 0 || t as usize > recipients.len() {
+        return Err(io::Error::other("invalid threshold"));
+    }
+    let (secret, commitments, shares) = crypto::pvss::deal(t, recipients);
+    let header = types::PvssHeader {
+        threshold: t as usize,
+        commitments,
+        shares,
+    };
+    let (output, _) = cookie_factory::gen(format::write::pvss_header(&header), output)
+        .map_err(io::Error::other)?;
+    encrypt_payload(&crypto::pvss::file_key(&secret), input, output)
+}
+
+/// Check, without any secret, that every share of a file made by [`encrypt_pvss`]
+/// is consistent with its commitments.
+///
+/// Returns the threshold and the number of shares.
+pub fn verify_pvss(input: &mut impl Read) -> io::Result<(usize, usize)> {
+    let header = read_pvss_header(&mut BufReader::new(input))?;
+    check_pvss_header(&header)?;
+    Ok((header.threshold, header.shares.len()))
+}
+
+/// Decrypt a file made by [`encrypt_pvss`]. All shares are verified first.
+pub fn decrypt_pvss(
+    identities: &[types::RistrettoIdentity],
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut input = BufReader::new(input);
+    let header = read_pvss_header(&mut input)?;
+    check_pvss_header(&header)?;
+
+    let mut decrypted = vec![];
+    for share in &header.shares {
+        if let Some(identity) = identities.iter().find(|i| i.to_public() == share.recipient) {
+            let (d, _) = crypto::pvss::decrypt_share(identity, share);
+            decrypted.push((share.index, d));
+        }
+    }
+    if decrypted.len() < header.threshold {
+        return Err(io::Error::other("not enough shares"));
+    }
+    let secret = crypto::pvss::combine(&decrypted[..header.threshold]);
+    decrypt_stream(&crypto::pvss::file_key(&secret), input, output)
+}
+
+fn check_pvss_header(header: &types::PvssHeader) -> io::Result<()> {
+    if header.threshold == 0 || header.commitments.len() != header.threshold {
+        return Err(io::Error::other("invalid threshold"));
+    }
+    for (i, share) in header.shares.iter().enumerate() {
+        if header.shares[..i].iter().any(|s| s.index == share.index) {
+            return Err(io::Error::other(format!("duplicate share {}", share.index)));
+        }
+        if !crypto::pvss::verify_share(share, &header.commitments) {
+            return Err(io::Error::other(format!(
+                "inconsistent share {}",
+                share.index
+            )));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! Moving and refreshing the shares of a file.
+
+use nom_bufreader::bufreader::BufReader;
+use std::io;
+use std::io::prelude::*;
+
+use crate::header::{read_header, write_header};
+use crate::shares::{encrypt_share, unlock_shares};
+use crate::{crypto, types};
+
+/// Move the shares that `identities` can unlock to `recipients`.
+///
+/// All the share groups of such an index, such as the ones appended by [`refresh`],
+/// are replaced by a single group holding their sum, wrapped to `recipients`;
+/// the commitments and the other share groups are left untouched,
+/// and the payload is copied as is.
+pub fn rewrap(
+    identities: &[types::AgeIdentity],
+    recipients: &[types::AgeRecipient],
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    if recipients.is_empty() {
+        return Err(io::Error::other("no recipients"));
+    }
+    let mut input = BufReader::new(input);
+    let mut header = read_header(&mut input)?;
+
+    let shares = unlock_shares(identities, &header)?;
+    if shares.is_empty() {
+        return Err(io::Error::other("no share could be unwrapped"));
+    }
+    let mut enc_shares: Vec<types::EncShare> = vec![];
+    for es in std::mem::take(&mut header.enc_shares) {
+        match shares.iter().find(|share| share.index == es.index) {
+            // The first group of an index takes the place of all of them.
+            Some(share) if !enc_shares.iter().any(|e| e.index == es.index) => {
+                enc_shares.push(encrypt_share(share, recipients, &header)?);
+            }
+            Some(_) => {}
+            None => enc_shares.push(es),
+        }
+    }
+    header.enc_shares = enc_shares;
+
+    let output = write_header(output, &header)?;
+    io::copy(&mut input, output)?;
+    Ok(())
+}
+
+/// Append a share group of zero-valued shares for each index and update the commitments.
+///
+/// `holders` maps every share index of the file to the recipients that its share is wrapped
+/// to; an index may have several recipients. Holders are named by index only, since the
+/// labels of other holders' shares are not authenticated.
+/// Holders keep decrypting as usual, since share groups with the same index are summed,
+/// but shares that leaked before the refresh no longer match the commitments.
+/// Each holder then completes the refresh with [`rewrap`], which replaces its groups by a
+/// single one. When the holders take turns refreshing, no one of them knows the new shares
+/// of the others.
+pub fn refresh(
+    holders: &[(u32, types::AgeRecipient)],
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut input = BufReader::new(input);
+    let mut header = read_header(&mut input)?;
+    let commitments = header.commitments.pedersen().map_err(io::Error::other)?;
+
+    let mut indices: Vec<_> = header.enc_shares.iter().map(|es| es.index).collect();
+    indices.sort();
+    indices.dedup();
+    let mut recipients = vec![vec![]; indices.len()];
+    for (index, r) in holders {
+        let Ok(k) = indices.binary_search(index) else {
+            return Err(io::Error::other(format!("no share index {}", index)));
+        };
+        recipients[k].push(r.clone());
+    }
+    if let Some(k) = recipients.iter().position(|r| r.is_empty()) {
+        return Err(io::Error::other(format!(
+            "no recipient for share index {}",
+            indices[k]
+        )));
+    }
+
+    let (deltas, delta_commitments) = crypto::refresh_shares(header.threshold as u32, &indices);
+    let commitments = crypto::add_commitments(commitments, &delta_commitments);
+    for (recipients, delta) in recipients.iter().zip(deltas.iter()) {
+        let es = encrypt_share(delta, recipients, &header)?;
+        header.enc_shares.push(es);
+    }
+    header.commitments = types::Commitments::Pedersen(commitments);
+
+    let output = write_header(output, &header)?;
+    io::copy(&mut input, output)?;
+    Ok(())
+}
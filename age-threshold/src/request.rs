@@ -0,0 +1,256 @@
+//! Share requests, for holders who answer from another machine.
+
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ed25519_dalek::Signer;
+use nom_bufreader::bufreader::BufReader;
+use std::io;
+use std::io::prelude::*;
+
+use crate::export_header_shares;
+use crate::header::{read_header, write_header};
+use crate::payload::decrypt_payload;
+use crate::shares::{add_shares, unlock_shares};
+use crate::types;
+
+const REQUEST_VERSION_LINE: &[u8] = b"age-threshold/request/v1\n";
+
+const REQUEST_SIGNATURE_LABEL: &[u8] = b"age-threshold request signature v1";
+
+/// Start an asynchronous decryption: write a request for the header of `input`.
+///
+/// The request holds the header and a fresh recipient for the responses.
+/// With a `signer`, the request is signed so that holders know who makes it.
+/// The returned identity must be kept by the coordinator for [`fulfill`].
+pub fn request(
+    signer: Option<&types::SigningKey>,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<age::x25519::Identity> {
+    let header = read_header(&mut BufReader::new(input))?;
+    let identity = age::x25519::Identity::generate();
+    write_request(&header, &identity.to_public(), signer, output)?;
+    Ok(identity)
+}
+
+/// Parse a request written by [`request`].
+pub fn read_request(input: &mut impl Read) -> io::Result<types::Request> {
+    let mut input = BufReader::new(input);
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    if line.as_bytes() != REQUEST_VERSION_LINE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a request"));
+    }
+    line.clear();
+    input.read_line(&mut line)?;
+    let recipient: age::x25519::Recipient = line.trim().parse().map_err(io::Error::other)?;
+    let mut signature = None;
+    if input.fill_buf()?.starts_with(b"ssh-ed25519 ") {
+        line.clear();
+        input.read_line(&mut line)?;
+        signature = Some(line.trim().to_string());
+    }
+    let header = read_header(&mut input)?;
+    let signer = match signature {
+        Some(line) => Some(verify_request(&header, &recipient, &line)?),
+        None => None,
+    };
+    Ok(types::Request {
+        recipient,
+        signer,
+        header,
+    })
+}
+
+/// Answer a request: encrypt one's own shares, as share files, to the coordinator.
+///
+/// Returns the fingerprint of the requested header, for holders to check out of band.
+pub fn respond(
+    identities: &[types::AgeIdentity],
+    label: &str,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<[u8; 32]> {
+    let request = read_request(input)?;
+    let share_files = request_shares(identities, label, &request)?;
+    respond_to(&request, &share_files, output)?;
+    Ok(request.fingerprint())
+}
+
+/// Unwrap one's own shares of a parsed request, so they can be reviewed before [`respond_to`].
+///
+/// The share files are labeled with `label`, or else with the labels of the header.
+pub fn request_shares(
+    identities: &[types::AgeIdentity],
+    label: &str,
+    request: &types::Request,
+) -> io::Result<Vec<types::ShareFile>> {
+    let mut share_files = export_header_shares(identities, &request.header)?;
+    if share_files.is_empty() {
+        return Err(io::Error::other("no share could be unwrapped"));
+    }
+    if !label.is_empty() {
+        for f in &mut share_files {
+            f.label = label.to_string();
+        }
+    }
+    Ok(share_files)
+}
+
+/// Answer a parsed request with shares from [`request_shares`], once they have been released.
+pub fn respond_to(
+    request: &types::Request,
+    share_files: &[types::ShareFile],
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut text = String::new();
+    for f in share_files {
+        text.push_str(&f.to_armor());
+    }
+
+    let encryptor =
+        age::Encryptor::with_recipients(vec![Box::new(request.recipient.clone())]).unwrap();
+    let output = ArmoredWriter::wrap_output(output, Format::AsciiArmor)?;
+    let mut w = encryptor.wrap_output(output).map_err(io::Error::other)?;
+    w.write_all(text.as_bytes())?;
+    w.finish()?.finish()?;
+    Ok(())
+}
+
+/// Decrypt `input` with the shares found in `responses` to a request made with [`request`].
+///
+/// Shares are verified and must belong to the header of `input`.
+pub fn fulfill(
+    identity: &age::x25519::Identity,
+    responses: &[Vec<u8>],
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut input = BufReader::new(input);
+    let header = read_header(&mut input)?;
+
+    let mut shares = vec![];
+    for response in responses {
+        add_shares(&mut shares, response_shares(identity, &header, response)?);
+    }
+    decrypt_payload(&header, &shares, input, output)
+}
+
+/// Decrypt `input` with the shares unlocked by `identities`, then with those of other holders.
+///
+/// While shares are missing, `ask` is called with a request and returns a response,
+/// or `None` when there is nobody left to ask. Invalid responses are skipped.
+pub fn decrypt_with_requests(
+    identities: &[types::AgeIdentity],
+    signer: Option<&types::SigningKey>,
+    mut ask: impl FnMut(&[u8]) -> Option<io::Result<Vec<u8>>>,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut input = BufReader::new(input);
+    let header = read_header(&mut input)?;
+
+    let mut shares = unlock_shares(identities, &header)?;
+    if shares.len() < header.threshold {
+        let identity = age::x25519::Identity::generate();
+        let mut request = vec![];
+        write_request(&header, &identity.to_public(), signer, &mut request)?;
+        let mut errors = vec![];
+        while shares.len() < header.threshold {
+            let Some(response) = ask(&request) else {
+                break;
+            };
+            match response.and_then(|r| response_shares(&identity, &header, &r)) {
+                Ok(v) => add_shares(&mut shares, v),
+                Err(err) => errors.push(err.to_string()),
+            }
+        }
+        if shares.len() < header.threshold && !errors.is_empty() {
+            return Err(io::Error::other(format!(
+                "not enough shares: {}",
+                errors.join(", ")
+            )));
+        }
+    }
+    decrypt_payload(&header, &shares, input, output)
+}
+
+fn write_request(
+    header: &types::Header,
+    recipient: &age::x25519::Recipient,
+    signer: Option<&types::SigningKey>,
+    mut output: impl Write,
+) -> io::Result<()> {
+    output.write_all(REQUEST_VERSION_LINE)?;
+    writeln!(output, "{}", recipient)?;
+    if let Some(signer) = signer {
+        let signature = signer.0.sign(&request_message(header, recipient));
+        writeln!(
+            output,
+            "{} {}",
+            signer.verifying_key(),
+            STANDARD.encode(signature.to_bytes())
+        )?;
+    }
+    write_header(output, header)?;
+    Ok(())
+}
+
+/// What the signature of a request covers: the header and the recipient of the responses,
+/// so that a signed request cannot be replayed to get shares encrypted to someone else.
+fn request_message(header: &types::Header, recipient: &age::x25519::Recipient) -> Vec<u8> {
+    [
+        REQUEST_SIGNATURE_LABEL,
+        &header.fingerprint(),
+        recipient.to_string().as_bytes(),
+    ]
+    .concat()
+}
+
+/// Check the signature line of a request, returning the key that made it.
+fn verify_request(
+    header: &types::Header,
+    recipient: &age::x25519::Recipient,
+    line: &str,
+) -> io::Result<types::VerifyingKey> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid request signature");
+    let (key, signature) = line.rsplit_once(' ').ok_or_else(invalid)?;
+    let key = types::VerifyingKey::from_openssh(key).map_err(|_| invalid())?;
+    let signature = STANDARD.decode(signature).map_err(|_| invalid())?;
+    let signature = ed25519_dalek::Signature::from_slice(&signature).map_err(|_| invalid())?;
+    key.0
+        .verify_strict(&request_message(header, recipient), &signature)
+        .map_err(|_| invalid())?;
+    Ok(key)
+}
+
+/// Decrypt a response and return its verified shares, which must belong to `header`.
+fn response_shares(
+    identity: &age::x25519::Identity,
+    header: &types::Header,
+    response: &[u8],
+) -> io::Result<Vec<types::SecretShare>> {
+    let fingerprint = header.fingerprint();
+    let age::Decryptor::Recipients(decryptor) =
+        age::Decryptor::new(ArmoredReader::new(response)).map_err(io::Error::other)?
+    else {
+        return Err(io::Error::other("response is not encrypted to a recipient"));
+    };
+    let mut text = String::new();
+    decryptor
+        .decrypt(std::iter::once(identity as &dyn age::Identity))
+        .map_err(io::Error::other)?
+        .read_to_string(&mut text)?;
+    let mut shares = vec![];
+    for block in text.split_inclusive(types::SHARE_ARMOR_END) {
+        if block.trim().is_empty() {
+            continue;
+        }
+        let f = types::ShareFile::from_armor(block).map_err(io::Error::other)?;
+        if f.fingerprint != fingerprint {
+            return Err(io::Error::other("response for another file"));
+        }
+        shares.push(f.share);
+    }
+    Ok(shares)
+}
@@ -0,0 +1,91 @@
+//! Sessions that collect shares across several runs.
+
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use age_core::secrecy::{SecretString, Zeroize};
+use nom_bufreader::bufreader::BufReader;
+use std::io;
+use std::io::prelude::*;
+
+use crate::header::read_header;
+use crate::shares::{share_files, unlock_shares};
+use crate::types;
+
+const SESSION_VERSION_LINE: &[u8] = b"age-threshold/session/v1\n";
+
+/// Add the shares that `identities` unlock to `session`, the shares collected for the same
+/// file by earlier runs, for holders who cannot be present at the same time.
+///
+/// The unlocked shares are passed to `approve` with the header before they join the session,
+/// so that they can be shown, checked and logged like any share leaving its holder.
+///
+/// Returns the number of shares collected and the threshold.
+pub fn collect_shares(
+    identities: &[types::AgeIdentity],
+    approve: impl FnOnce(&types::Header, &[types::ShareFile]) -> io::Result<()>,
+    session: &mut Vec<types::ShareFile>,
+    input: &mut impl Read,
+) -> io::Result<(usize, usize)> {
+    let header = read_header(&mut BufReader::new(input))?;
+    let fingerprint = header.fingerprint();
+    if session.iter().any(|f| f.fingerprint != fingerprint) {
+        return Err(io::Error::other("session belongs to another file"));
+    }
+    let new = share_files(&header, unlock_shares(identities, &header)?)?;
+    if new.is_empty() {
+        return Err(io::Error::other("no share could be unwrapped"));
+    }
+    approve(&header, &new)?;
+    for f in new {
+        if !session.iter().any(|s| s.share.index == f.share.index) {
+            session.push(f);
+        }
+    }
+    Ok((session.len(), header.threshold))
+}
+
+/// Encrypt the shares of a session with `passphrase`, to keep them between runs.
+pub fn write_session(
+    session: &[types::ShareFile],
+    passphrase: SecretString,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut text = String::from_utf8(SESSION_VERSION_LINE.to_vec()).unwrap();
+    for f in session {
+        text.push_str(&f.to_armor());
+    }
+    let encryptor = age::Encryptor::with_user_passphrase(passphrase);
+    let output = ArmoredWriter::wrap_output(output, Format::AsciiArmor)?;
+    let mut w = encryptor.wrap_output(output).map_err(io::Error::other)?;
+    w.write_all(text.as_bytes())?;
+    text.zeroize();
+    w.finish()?.finish()?;
+    Ok(())
+}
+
+/// Decrypt a session written by [`write_session`]. Shares are verified.
+pub fn read_session(
+    passphrase: SecretString,
+    input: &mut impl Read,
+) -> io::Result<Vec<types::ShareFile>> {
+    let age::Decryptor::Passphrase(decryptor) =
+        age::Decryptor::new(ArmoredReader::new(input)).map_err(io::Error::other)?
+    else {
+        return Err(io::Error::other("session is not passphrase-encrypted"));
+    };
+    let mut text = String::new();
+    decryptor
+        .decrypt(&passphrase, None)
+        .map_err(io::Error::other)?
+        .read_to_string(&mut text)?;
+    let Some(body) = text.strip_prefix(std::str::from_utf8(SESSION_VERSION_LINE).unwrap()) else {
+        return Err(io::Error::other("not a session"));
+    };
+    let mut session = vec![];
+    for block in body.split_inclusive(types::SHARE_ARMOR_END) {
+        if !block.trim().is_empty() {
+            session.push(types::ShareFile::from_armor(block).map_err(io::Error::other)?);
+        }
+    }
+    text.zeroize();
+    Ok(session)
+}
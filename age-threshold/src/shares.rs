@@ -0,0 +1,296 @@
+//! Encryption, decryption and verification of the shares of the file key.
+
+use age::cli_common::UiCallbacks;
+use age_core::format::{FileKey, Stanza};
+use age_core::primitives::hkdf;
+use age_core::secrecy::{ExposeSecret, Zeroize};
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+use chacha20poly1305::{AeadInPlace, ChaCha20Poly1305, KeyInit};
+use curve25519_dalek::scalar::Scalar;
+use std::io;
+
+use crate::header::new_file_key;
+use crate::{crypto, types};
+
+const SHARE_KEY_LABEL: &[u8] = b"age-threshold share v1";
+
+const KEY_COMMITMENT_LABEL: &[u8] = b"age-threshold key commitment";
+
+/// Add shares whose index is not already present.
+pub(crate) fn add_shares(shares: &mut Vec<types::SecretShare>, new: Vec<types::SecretShare>) {
+    for share in new {
+        if !shares.iter().any(|s| s.index == share.index) {
+            shares.push(share);
+        }
+    }
+}
+
+pub(crate) fn share_files(
+    header: &types::Header,
+    shares: Vec<types::SecretShare>,
+) -> io::Result<Vec<types::ShareFile>> {
+    let threshold = header.threshold as u32;
+    let commitments = header.commitments.pedersen().map_err(io::Error::other)?;
+    let fingerprint = header.fingerprint();
+    let created = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(io::Error::other)?
+        .as_secs();
+    Ok(shares
+        .into_iter()
+        .map(|share| types::ShareFile {
+            fingerprint,
+            threshold,
+            commitments: commitments.to_vec(),
+            label: header.label(share.index).unwrap_or_default().to_string(),
+            created,
+            share,
+        })
+        .collect())
+}
+
+/// The error for too few `shares`, naming the holders of the others.
+/// The missing shares were not decrypted, so their labels are not authenticated.
+fn not_enough_shares(header: &types::Header, shares: &[types::SecretShare]) -> io::Error {
+    let mut missing: Vec<String> = vec![];
+    for es in &header.enc_shares {
+        let name = match header.label(es.index) {
+            Some(label) => format!("{} ({}, unverified)", es.index, label),
+            None => es.index.to_string(),
+        };
+        if !shares.iter().any(|s| s.index == es.index) && !missing.contains(&name) {
+            missing.push(name);
+        }
+    }
+    io::Error::other(format!("not enough shares, missing {}", missing.join(", ")))
+}
+
+pub(crate) fn reconstruct_file_key(
+    header: &types::Header,
+    shares: &[types::SecretShare],
+) -> io::Result<FileKey> {
+    if shares.len() < header.threshold {
+        return Err(not_enough_shares(header, shares));
+    }
+    let file_key = crypto::reconstruct_secret(&shares[..header.threshold]);
+    check_key_commitment(header, &file_key)?;
+    Ok(file_key)
+}
+
+/// A digest of the file key, which pins it since the payload AEAD does not.
+pub(crate) fn key_commitment(file_key: &FileKey) -> [u8; 32] {
+    hkdf(&[], KEY_COMMITMENT_LABEL, file_key.expose_secret())
+}
+
+/// Check a reconstructed file key against the key commitment of the header,
+/// which every header from [`types::HeaderVersion::V1`] on must have.
+fn check_key_commitment(header: &types::Header, file_key: &FileKey) -> io::Result<()> {
+    let Some(e) = header
+        .extensions
+        .iter()
+        .find(|e| e.name == types::KEY_COMMITMENT_EXTENSION)
+    else {
+        if header.version >= types::HeaderVersion::V1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "missing key commitment",
+            ));
+        }
+        return Ok(());
+    };
+    if !e.args.is_empty() || e.body[..] != key_commitment(file_key) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "file key does not match its commitment",
+        ));
+    }
+    Ok(())
+}
+
+/// Encrypt each share to the recipient at the same position.
+pub(crate) fn encrypt_shares(
+    shares: &[types::SecretShare],
+    recipients: &[types::AgeRecipient],
+    header: &types::Header,
+) -> io::Result<Vec<types::EncShare>> {
+    let mut enc_shares = vec![];
+    for (r, share) in recipients.iter().zip(shares.iter()) {
+        enc_shares.push(encrypt_share(share, std::slice::from_ref(r), header)?);
+    }
+    Ok(enc_shares)
+}
+
+/// The unauthenticated cipher of legacy `share` stanzas, only used in version 0 headers.
+fn share_cipher(share_key: &FileKey) -> ChaCha20 {
+    ChaCha20::new(
+        &hkdf(&[], b"", &share_key.expose_secret()[..]).into(),
+        (&[0; 12]).into(),
+    )
+}
+
+fn share_aead(share_key: &FileKey) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(&hkdf(&[], SHARE_KEY_LABEL, &share_key.expose_secret()[..]).into())
+}
+
+/// The associated data of a `share-v1` stanza: its index, the context of its header,
+/// and the label of its holder if any.
+fn share_aad(index: u32, header: &types::Header) -> Vec<u8> {
+    let mut aad = index.to_le_bytes().to_vec();
+    aad.extend_from_slice(&header.share_context());
+    aad.extend_from_slice(header.label(index).unwrap_or_default().as_bytes());
+    aad
+}
+
+pub(crate) fn wrap_share_key(
+    share_key: &FileKey,
+    recipients: &[types::AgeRecipient],
+) -> io::Result<Vec<Stanza>> {
+    let mut stanzas = vec![];
+    for r in recipients {
+        let recipient = r.to_recipient(UiCallbacks).map_err(io::Error::other)?;
+        stanzas.extend(
+            recipient
+                .wrap_file_key(share_key)
+                .map_err(io::Error::other)?,
+        );
+    }
+    Ok(stanzas)
+}
+
+pub(crate) fn encrypt_share(
+    share: &types::SecretShare,
+    recipients: &[types::AgeRecipient],
+    header: &types::Header,
+) -> io::Result<types::EncShare> {
+    let share_key = new_file_key();
+    let mut buf = [share.s.to_bytes(), share.t.to_bytes()].concat();
+    // Readers of version 0 headers only know legacy `share` stanzas.
+    let tag = if header.version < types::HeaderVersion::V1 {
+        share_cipher(&share_key).apply_keystream(&mut buf);
+        None
+    } else {
+        let tag = share_aead(&share_key)
+            .encrypt_in_place_detached(&[0; 12].into(), &share_aad(share.index, header), &mut buf)
+            .map_err(io::Error::other)?;
+        Some(tag.into())
+    };
+    Ok(types::EncShare {
+        index: share.index,
+        s: buf[..32].try_into().unwrap(),
+        t: buf[32..].try_into().unwrap(),
+        tag,
+        stanzas: wrap_share_key(&share_key, recipients)?,
+    })
+}
+
+fn decrypt_share(
+    es: &types::EncShare,
+    share_key: &FileKey,
+    header: &types::Header,
+) -> io::Result<types::SecretShare> {
+    let mut buf = [es.s, es.t].concat();
+    match &es.tag {
+        Some(tag) => share_aead(share_key)
+            .decrypt_in_place_detached(
+                &[0; 12].into(),
+                &share_aad(es.index, header),
+                &mut buf,
+                tag.into(),
+            )
+            .map_err(|_| io::Error::other("share decryption failed"))?,
+        None => share_cipher(share_key).apply_keystream(&mut buf),
+    }
+    let share = types::SecretShare {
+        index: es.index,
+        s: Scalar::from_bytes_mod_order(buf[..32].try_into().unwrap()),
+        t: Scalar::from_bytes_mod_order(buf[32..].try_into().unwrap()),
+    };
+    buf.zeroize();
+    Ok(share)
+}
+
+pub(crate) fn unlock_shares(
+    identities: &[types::AgeIdentity],
+    header: &types::Header,
+) -> io::Result<Vec<types::SecretShare>> {
+    let share_keys = unwrap_share_keys(identities, header)?;
+    sum_shares(header, &share_keys)
+}
+
+/// Unwrap the share key of each share group, skipping indices that cannot be fully unwrapped.
+fn unwrap_share_keys(
+    identities: &[types::AgeIdentity],
+    header: &types::Header,
+) -> io::Result<Vec<Option<FileKey>>> {
+    let mut missing = vec![];
+    let mut share_keys = vec![];
+    for es in &header.enc_shares {
+        let share_key = if missing.contains(&es.index) {
+            None
+        } else {
+            decrypt_fk(identities, es)?
+        };
+        if share_key.is_none() {
+            missing.push(es.index);
+        }
+        share_keys.push(share_key);
+    }
+    Ok(share_keys)
+}
+
+/// Decrypt and add up the share groups of each index, then verify the resulting shares.
+fn sum_shares(
+    header: &types::Header,
+    share_keys: &[Option<FileKey>],
+) -> io::Result<Vec<types::SecretShare>> {
+    let mut shares: Vec<types::SecretShare> = vec![];
+    let mut missing = vec![];
+    for (es, share_key) in header.enc_shares.iter().zip(share_keys) {
+        let Some(share_key) = share_key else {
+            missing.push(es.index);
+            continue;
+        };
+        let piece = decrypt_share(es, share_key, header)?;
+        match shares.iter_mut().find(|share| share.index == es.index) {
+            Some(share) => {
+                share.s += piece.s;
+                share.t += piece.t;
+            }
+            None => shares.push(piece),
+        }
+    }
+    shares.retain(|share| !missing.contains(&share.index));
+    if !shares.iter().all(|share| verify_share(header, share)) {
+        return Err(io::Error::other("invalid share"));
+    }
+    Ok(shares)
+}
+
+/// Check a share against the commitments of `header`.
+pub(crate) fn verify_share(header: &types::Header, share: &types::SecretShare) -> bool {
+    match &header.commitments {
+        types::Commitments::Pedersen(c) => crypto::verify_share(share, c),
+        types::Commitments::Hash(c) => crypto::hash_vss::verify_share(share, c),
+    }
+}
+
+fn decrypt_fk(
+    identities: &[types::AgeIdentity],
+    es: &types::EncShare,
+) -> io::Result<Option<FileKey>> {
+    for identity in identities {
+        for s in &es.stanzas {
+            match identity
+                .to_identity(UiCallbacks)
+                .map_err(io::Error::other)?
+                .unwrap_stanza(s)
+            {
+                Some(Ok(file_key)) => return Ok(Some(file_key)),
+                Some(Err(err)) => return Err(io::Error::other(err)),
+                None => continue,
+            }
+        }
+    }
+    Ok(None)
+}
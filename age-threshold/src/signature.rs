@@ -0,0 +1,165 @@
+//! Signed headers and payloads.
+
+use age_core::format::FileKey;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ed25519_dalek::Signer;
+use nom_bufreader::bufreader::BufReader;
+use sha2::{Digest, Sha256};
+use std::io;
+use std::io::prelude::*;
+
+use crate::header::read_header;
+use crate::payload::{decrypt_padded_stream, decrypt_payload};
+use crate::shares::unlock_shares;
+use crate::{format, types};
+
+const SIGNATURE_LABEL: &[u8] = b"age-threshold signature v1";
+
+/// Verify the signature of a header, returning the key that made it, if the header is signed.
+///
+/// The signature covers the share context, that is the threshold and, for Pedersen
+/// commitments, the first one, which pins the file key; the other extensions, such as labels,
+/// metadata, the key commitment and padding; and the digest of the payload, which is checked
+/// before the payload is decrypted. It does not cover the share stanzas, nor the other
+/// commitments, so that it survives rewrapping, refreshing and enrolling shares.
+pub fn signer(header: &types::Header) -> io::Result<Option<types::VerifyingKey>> {
+    Ok(signature(header)?.map(|(key, _)| key))
+}
+
+/// Like [`decrypt`](crate::decrypt), refusing files that are not signed by one of `signers`.
+pub fn decrypt_signed(
+    identities: &[types::AgeIdentity],
+    signers: &[types::VerifyingKey],
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut input = BufReader::new(input);
+    let header = read_header(&mut input)?;
+    match signer(&header)? {
+        Some(key) if signers.contains(&key) => {}
+        Some(key) => return Err(io::Error::other(format!("unexpected signer {}", key))),
+        None => return Err(io::Error::other("file is not signed")),
+    }
+
+    let shares = unlock_shares(identities, &header)?;
+    decrypt_payload(&header, &shares, input, output)
+}
+
+/// The message signed by the sender of a file: the share context, every extension but the
+/// signature itself as it is written in the header, then the payload digest.
+fn signed_message(header: &types::Header, digest: &[u8; 32]) -> Vec<u8> {
+    let mut message = [SIGNATURE_LABEL, &header.share_context()].concat();
+    for e in &header.extensions {
+        if e.name != types::SIGNATURE_EXTENSION {
+            (message, _) = cookie_factory::gen(format::write::extension(e), message)
+                .expect("writing to a vector cannot fail");
+        }
+    }
+    message.extend(digest);
+    message
+}
+
+pub(crate) fn sign_header(
+    header: &types::Header,
+    signer: &types::SigningKey,
+    digest: &[u8; 32],
+) -> types::Extension {
+    let signature = signer.0.sign(&signed_message(header, digest));
+    types::Extension {
+        critical: false,
+        name: types::SIGNATURE_EXTENSION.to_string(),
+        args: vec![
+            "ssh-ed25519".to_string(),
+            STANDARD.encode(signer.verifying_key().as_bytes()),
+            STANDARD.encode(digest),
+        ],
+        body: signature.to_bytes().to_vec(),
+    }
+}
+
+/// Verify the signature extension of a header, returning the signer and the payload digest.
+pub(crate) fn signature(
+    header: &types::Header,
+) -> io::Result<Option<(types::VerifyingKey, [u8; 32])>> {
+    let Some(e) = header
+        .extensions
+        .iter()
+        .find(|e| e.name == types::SIGNATURE_EXTENSION)
+    else {
+        return Ok(None);
+    };
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid signature");
+    let [kind, key, digest] = &e.args[..] else {
+        return Err(invalid());
+    };
+    if kind != "ssh-ed25519" {
+        return Err(io::Error::other(format!(
+            "unsupported signature type {}",
+            kind
+        )));
+    }
+    let decode = |arg: &str| -> io::Result<[u8; 32]> {
+        STANDARD
+            .decode(arg)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(invalid)
+    };
+    let key = types::VerifyingKey::from_bytes(&decode(key)?).map_err(|_| invalid())?;
+    let digest = decode(digest)?;
+    let signature = ed25519_dalek::Signature::from_slice(&e.body).map_err(|_| invalid())?;
+    key.0
+        .verify_strict(&signed_message(header, &digest), &signature)
+        .map_err(|_| invalid())?;
+    Ok(Some((key, digest)))
+}
+
+/// A writer that hashes everything written through it.
+pub(crate) struct HashingWriter<W> {
+    pub(crate) inner: W,
+    pub(crate) hasher: Sha256,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypt the payload, checking it against the signed digest if the header is signed.
+///
+/// The payload is only authenticated by the file key, so without this check a quorum of
+/// holders could swap it under a header signed by someone else.
+///
+/// The digest can only be checked at the end, so a signed payload is first copied to a
+/// temporary file while it is hashed, and only decrypted once the digest matches.
+pub(crate) fn decrypt_signed_stream(
+    header: &types::Header,
+    file_key: &FileKey,
+    mut input: BufReader<impl Read>,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let Some((_, digest)) = signature(header)? else {
+        return decrypt_padded_stream(header, file_key, input, output);
+    };
+    let mut payload = HashingWriter {
+        inner: tempfile::tempfile()?,
+        hasher: Sha256::new(),
+    };
+    io::copy(&mut input, &mut payload)?;
+    if payload.hasher.finalize()[..] != digest {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "payload does not match its signature",
+        ));
+    }
+    let mut payload = payload.inner;
+    payload.rewind()?;
+    decrypt_padded_stream(header, file_key, BufReader::new(payload), output)
+}
@@ -0,0 +1,126 @@
+//! Threshold splitting of age identities.
+
+use age_core::format::FileKey;
+use age_core::primitives::hkdf;
+use age_core::secrecy::{ExposeSecret, SecretString};
+use chacha20poly1305::{AeadInPlace, ChaCha20Poly1305, KeyInit};
+use std::io;
+
+use crate::header::new_file_key;
+use crate::{crypto, types};
+
+/// Split an age identity into `n` printable shares, any `threshold` of which restore it.
+///
+/// The identity is encrypted under a fresh key, and that key is shared with the usual
+/// verifiable secret sharing; every share carries the commitments and the ciphertext.
+pub fn split_identity(
+    identity: &types::AgeIdentity,
+    threshold: u32,
+    n: u32,
+) -> io::Result<Vec<types::IdentityShare>> {
+    if threshold == 0 || threshold > n {
+        return Err(io::Error::other("invalid threshold"));
+    }
+    let key = new_file_key();
+    let mut ciphertext = identity.to_bech32().expose_secret().as_bytes().to_vec();
+    identity_cipher(&key)
+        .encrypt_in_place(&[0; 12].into(), b"", &mut ciphertext)
+        .map_err(io::Error::other)?;
+    let (shares, commitments) = crypto::share_secret(&key, threshold, n);
+    Ok(shares
+        .into_iter()
+        .map(|share| types::IdentityShare {
+            threshold,
+            share,
+            commitments: commitments.clone(),
+            ciphertext: ciphertext.clone(),
+        })
+        .collect())
+}
+
+/// Restore an identity split with [`split_identity`] from at least its threshold of shares.
+pub fn combine_identity(shares: &[types::IdentityShare]) -> io::Result<types::AgeIdentity> {
+    let first = shares.first().ok_or(io::Error::other("no shares"))?;
+    let mut secret_shares: Vec<types::SecretShare> = vec![];
+    for s in shares {
+        if s.commitments != first.commitments || s.ciphertext != first.ciphertext {
+            return Err(io::Error::other("shares of different identities"));
+        }
+        if !crypto::verify_share(&s.share, &s.commitments) {
+            return Err(io::Error::other("invalid share"));
+        }
+        if !secret_shares.iter().any(|o| o.index == s.share.index) {
+            secret_shares.push(s.share.clone());
+        }
+    }
+    if secret_shares.len() < first.threshold as usize {
+        return Err(io::Error::other("not enough shares"));
+    }
+    let key = crypto::reconstruct_secret(&secret_shares[..first.threshold as usize]);
+
+    let mut plaintext = first.ciphertext.clone();
+    identity_cipher(&key)
+        .decrypt_in_place(&[0; 12].into(), b"", &mut plaintext)
+        .map_err(|_| io::Error::other("identity decryption failed"))?;
+    let identity = String::from_utf8(plaintext).map_err(io::Error::other)?;
+    let identity = SecretString::new(identity);
+    types::AgeIdentity::from_bech32(identity.expose_secret()).map_err(io::Error::other)
+}
+
+/// Split a secret of any length into `n` shares, any `threshold` of which restore it.
+pub fn split(secret: &[u8], threshold: u32, n: u32) -> io::Result<Vec<types::SplitShare>> {
+    if threshold == 0 || threshold > n {
+        return Err(io::Error::other("invalid threshold"));
+    }
+    if secret.is_empty() {
+        return Err(io::Error::other("empty secret"));
+    }
+    let length = u32::try_from(secret.len()).map_err(|_| io::Error::other("secret too long"))?;
+    let (holders, commitments) = crypto::share_bytes(secret, threshold, n);
+    Ok(holders
+        .into_iter()
+        .map(|shares| types::SplitShare {
+            threshold,
+            length,
+            shares,
+            commitments: commitments.clone(),
+        })
+        .collect())
+}
+
+/// Restore a secret split with [`split`] from at least its threshold of shares.
+pub fn combine(shares: &[types::SplitShare]) -> io::Result<Vec<u8>> {
+    let first = shares.first().ok_or(io::Error::other("no shares"))?;
+    let mut holders: Vec<Vec<types::SecretShare>> = vec![];
+    for s in shares {
+        if s.threshold != first.threshold
+            || s.length != first.length
+            || s.commitments != first.commitments
+        {
+            return Err(io::Error::other("shares of different secrets"));
+        }
+        let valid = s.shares.len() == s.commitments.len()
+            && s.shares.iter().all(|share| share.index == s.index())
+            && s.shares
+                .iter()
+                .zip(s.commitments.iter())
+                .all(|(share, c)| crypto::verify_share(share, c));
+        if !valid {
+            return Err(io::Error::other("invalid share"));
+        }
+        if !holders.iter().any(|h| h[0].index == s.index()) {
+            holders.push(s.shares.clone());
+        }
+    }
+    if holders.len() < first.threshold as usize {
+        return Err(io::Error::other("not enough shares"));
+    }
+    Ok(crypto::reconstruct_bytes(
+        &holders[..first.threshold as usize],
+        first.length as usize,
+    ))
+}
+
+fn identity_cipher(key: &FileKey) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(&hkdf(&[], b"identity", &key.expose_secret()[..]).into())
+}
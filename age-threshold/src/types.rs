@@ -1,26 +1,252 @@
+mod committee;
+mod encoding;
 mod identity;
+mod identity_share;
+mod metadata;
+mod mnemonic;
+mod padding;
 mod recipient;
+mod ristretto;
 mod secret_share;
+mod share_file;
+mod signing;
+mod split_share;
+mod threshold_recipient;
 
 use age_core::format::Stanza;
 
 use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
 
+pub use committee::{
+    CommitteeDealing, CommitteeIdentity, CommitteeRecipient, CommitteeReveal, PartialDecryption,
+};
 pub use identity::AgeIdentity;
+pub use identity_share::IdentityShare;
+pub use metadata::Metadata;
+pub use mnemonic::{ShareMnemonic, MNEMONIC_WORDS};
+pub use padding::Padding;
 pub use recipient::AgeRecipient;
+pub use ristretto::{RistrettoIdentity, RistrettoRecipient};
 pub use secret_share::SecretShare;
+pub use share_file::{ShareFile, SHARE_ARMOR_BEGIN, SHARE_ARMOR_END};
+pub use signing::{SigningKey, VerifyingKey};
+pub use split_share::SplitShare;
+pub use threshold_recipient::{ThresholdIdentity, ThresholdRecipient, PLUGIN_NAME};
 
 #[derive(Debug)]
 pub struct Header {
+    pub version: HeaderVersion,
     pub threshold: usize,
-    pub commitments: Vec<RistrettoPoint>,
+    pub commitments: Commitments,
+    pub extensions: Vec<Extension>,
     pub enc_shares: Vec<EncShare>,
 }
 
+impl Header {
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let threshold = self.threshold as u32;
+        match &self.commitments {
+            Commitments::Pedersen(c) => crate::crypto::header_fingerprint(threshold, c),
+            Commitments::Hash(c) => crate::crypto::hash_vss::header_fingerprint(threshold, c),
+        }
+    }
+
+    /// What `share-v1` stanzas are bound to. Unlike the fingerprint, it survives refreshes.
+    pub fn share_context(&self) -> [u8; 32] {
+        let threshold = self.threshold as u32;
+        match &self.commitments {
+            Commitments::Pedersen(c) => crate::crypto::share_context(threshold, c),
+            Commitments::Hash(c) => crate::crypto::hash_vss::header_fingerprint(threshold, c),
+        }
+    }
+
+    /// The label of the holder of share `index`, from its `label` extension.
+    pub fn label(&self, index: u32) -> Option<&str> {
+        self.extensions
+            .iter()
+            .find(|e| e.name == LABEL_EXTENSION && e.args == [index.to_string()])
+            .and_then(|e| std::str::from_utf8(&e.body).ok())
+    }
+
+    /// Whether the payload is padded, as marked by the `padding` extension.
+    pub fn padded(&self) -> bool {
+        self.extensions.iter().any(|e| e.name == PADDING_EXTENSION)
+    }
+}
+
+/// Name of the optional extension that labels the holder of a share index.
+pub const LABEL_EXTENSION: &str = "label";
+/// Name of the optional extension that holds the encrypted [`Metadata`].
+pub const METADATA_EXTENSION: &str = "metadata";
+/// Name of the optional extension that holds the signature of the sender.
+pub const SIGNATURE_EXTENSION: &str = "signature";
+/// Name of the critical extension that commits the header to its file key.
+pub const KEY_COMMITMENT_EXTENSION: &str = "key-commitment";
+/// Name of the critical extension that marks a padded payload.
+pub const PADDING_EXTENSION: &str = "padding";
+
+/// The version of a threshold header. Headers are read in any version and written in the newest
+/// one, unless pinned with [`EncryptOptions`]; rewriting a header keeps its version.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HeaderVersion {
+    /// Counts payload chunks little-endian.
+    V0,
+    /// Adds extension stanzas, among which a mandatory key commitment, and counts payload chunks
+    /// big-endian as age does.
+    #[default]
+    V1,
+}
+
+/// An extension stanza of a header, from [`HeaderVersion::V1`] on.
+///
+/// Readers skip optional extensions they do not know, and refuse files with critical ones.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Extension {
+    pub critical: bool,
+    pub name: String,
+    pub args: Vec<String>,
+    pub body: Vec<u8>,
+}
+
+/// Options for encrypting to a quorum.
+#[derive(Clone, Debug, Default)]
+pub struct EncryptOptions {
+    pub scheme: CommitmentScheme,
+    pub version: HeaderVersion,
+    /// Labels of the holders, in the order of the recipients, such as their names.
+    pub labels: Vec<Option<String>>,
+    /// Metadata about the plaintext, only readable by a quorum.
+    pub metadata: Option<Metadata>,
+    /// Key to sign the header and the payload with, so that holders know who sent the file.
+    pub signer: Option<SigningKey>,
+    /// Padding of the plaintext, so that the file does not reveal its exact length.
+    pub padding: Option<Padding>,
+}
+
+/// Which commitment scheme to verify the shares of a header with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CommitmentScheme {
+    #[default]
+    Pedersen,
+    Hash,
+}
+
+impl std::str::FromStr for CommitmentScheme {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pedersen" => Ok(CommitmentScheme::Pedersen),
+            "hash" => Ok(CommitmentScheme::Hash),
+            _ => Err("unknown commitment scheme"),
+        }
+    }
+}
+
+/// The commitments of a header, according to its [`CommitmentScheme`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Commitments {
+    /// Pedersen commitments to the coefficients of the sharing polynomials.
+    Pedersen(Vec<RistrettoPoint>),
+    /// A digest of each share, see [`crate::crypto::hash_vss`].
+    Hash(HashCommitments),
+}
+
+impl Commitments {
+    /// The Pedersen commitments, which share files, enrollment and refreshing rely on.
+    pub fn pedersen(&self) -> Result<&[RistrettoPoint], &'static str> {
+        match self {
+            Commitments::Pedersen(c) => Ok(c),
+            Commitments::Hash(_) => Err("not supported for files with hash commitments"),
+        }
+    }
+}
+
+/// Hash commitments to the shares of a header, see [`crate::crypto::hash_vss`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HashCommitments {
+    /// The coefficients of the masked polynomial `g + d · f`.
+    pub masked: Vec<Scalar>,
+    /// The digest of each share, from index 1.
+    pub digests: Vec<[u8; 32]>,
+}
+
 #[derive(Debug)]
 pub struct EncShare {
     pub index: u32,
     pub s: [u8; 32],
     pub t: [u8; 32],
+    /// The tag of an authenticated `share-v1` stanza, or `None` for a legacy masked share.
+    pub tag: Option<[u8; 16]>,
+    pub stanzas: Vec<Stanza>,
+}
+
+/// The header of a file whose shares are publicly verifiable, see [`crate::crypto::pvss`].
+#[derive(Debug)]
+pub struct PvssHeader {
+    pub threshold: usize,
+    pub commitments: Vec<RistrettoPoint>,
+    pub shares: Vec<PvssShare>,
+}
+
+/// A share encrypted to a ristretto255 holder key, with its proof of consistency.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PvssShare {
+    pub index: u32,
+    pub recipient: RistrettoRecipient,
+    pub encrypted: RistrettoPoint,
+    pub proof: crate::crypto::dleq::DleqProof,
+}
+
+/// The header of a file encrypted to a committee, see [`crate::crypto::committee`].
+#[derive(Debug)]
+pub struct CommitteeHeader {
+    pub threshold: usize,
+    pub commitments: Vec<RistrettoPoint>,
+    pub ephemeral: RistrettoPoint,
+}
+
+impl CommitteeHeader {
+    pub fn fingerprint(&self) -> [u8; 32] {
+        crate::crypto::committee::header_fingerprint(
+            self.threshold as u32,
+            &self.commitments,
+            &self.ephemeral,
+        )
+    }
+
+    pub fn recipient(&self) -> CommitteeRecipient {
+        CommitteeRecipient {
+            commitments: self.commitments.clone(),
+        }
+    }
+}
+
+/// The header of a plain age v1 file.
+#[derive(Debug)]
+pub struct AgeHeader {
     pub stanzas: Vec<Stanza>,
+    pub mac: [u8; 32],
+}
+
+/// A request for shares, as written by `request`: a header and where to send the shares.
+pub struct Request {
+    pub recipient: age::x25519::Recipient,
+    /// The long-term key that signed the request, once verified.
+    pub signer: Option<VerifyingKey>,
+    pub header: Header,
+}
+
+impl Request {
+    pub fn fingerprint(&self) -> [u8; 32] {
+        self.header.fingerprint()
+    }
+}
+
+/// A member's contribution to a committee key, as written by `committee_deal`:
+/// shares of a random secret, one per member, in a threshold header.
+pub struct Deal {
+    pub dealer: u32,
+    pub header: Header,
 }
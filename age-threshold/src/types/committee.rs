@@ -0,0 +1,312 @@
+use std::fmt;
+
+use age_core::secrecy::{SecretString, Zeroize};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+
+use super::encoding::{decode_long, Bech32Long};
+use crate::crypto::dleq::DleqProof;
+
+const RECIPIENT_HRP: &str = "age1committee";
+const IDENTITY_HRP: &str = "age-committee-secret-key-";
+const DEALING_HRP: &str = "age-committee-dealing-";
+const REVEAL_HRP: &str = "age-committee-reveal-";
+const PARTIAL_HRP: &str = "age-committee-partial-";
+
+/// The public key of a committee, as the commitments to the coefficients of its key polynomial.
+///
+/// The first commitment is the group key; there are as many as the threshold.
+/// See [`crate::crypto::committee`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitteeRecipient {
+    pub commitments: Vec<RistrettoPoint>,
+}
+
+impl CommitteeRecipient {
+    pub fn threshold(&self) -> usize {
+        self.commitments.len()
+    }
+
+    pub fn group_key(&self) -> RistrettoPoint {
+        self.commitments[0]
+    }
+
+    pub fn to_bech32(&self) -> String {
+        let hrp = bech32::Hrp::parse(RECIPIENT_HRP).unwrap();
+        bech32::encode::<Bech32Long>(hrp, &put_points(vec![], &self.commitments)).unwrap()
+    }
+
+    pub fn from_bech32(s: &str) -> Result<Self, &'static str> {
+        let (hrp, data) = decode_long(s)?;
+        if hrp.as_str() != RECIPIENT_HRP {
+            return Err("invalid HRP");
+        }
+        let commitments = get_points(&data)?;
+        if commitments.is_empty() {
+            return Err("invalid data length");
+        }
+        Ok(CommitteeRecipient { commitments })
+    }
+}
+
+impl fmt::Display for CommitteeRecipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_bech32())
+    }
+}
+
+/// A committee member's share of the group secret, along with the committee it belongs to.
+#[derive(Clone)]
+pub struct CommitteeIdentity {
+    pub index: u32,
+    secret: Scalar,
+    pub recipient: CommitteeRecipient,
+}
+
+impl CommitteeIdentity {
+    /// Fails unless `secret` matches the verification key of `index` in `recipient`.
+    pub(crate) fn new(
+        index: u32,
+        secret: Scalar,
+        recipient: CommitteeRecipient,
+    ) -> Result<Self, &'static str> {
+        let key = crate::crypto::committee::verification_key(&recipient, index);
+        if index == 0 || RISTRETTO_BASEPOINT_POINT * secret != key {
+            return Err("secret does not match the committee");
+        }
+        Ok(CommitteeIdentity {
+            index,
+            secret,
+            recipient,
+        })
+    }
+
+    pub(crate) fn secret(&self) -> &Scalar {
+        &self.secret
+    }
+
+    pub fn to_bech32(&self) -> SecretString {
+        let hrp = bech32::Hrp::parse(IDENTITY_HRP).unwrap();
+        let mut buf = self.index.to_le_bytes().to_vec();
+        buf.extend_from_slice(self.secret.as_bytes());
+        let mut buf = put_points(buf, &self.recipient.commitments);
+        let encoded = bech32::encode::<Bech32Long>(hrp, &buf)
+            .unwrap()
+            .to_uppercase();
+        buf.zeroize();
+        SecretString::new(encoded)
+    }
+
+    pub fn from_bech32(s: &str) -> Result<Self, &'static str> {
+        let (hrp, mut data) = decode_long(s)?;
+        if hrp.as_str().to_lowercase() != IDENTITY_HRP {
+            return Err("invalid HRP");
+        }
+        if data.len() < 36 {
+            return Err("invalid data length");
+        }
+        let index = u32::from_le_bytes(data[..4].try_into().unwrap());
+        let secret = Option::from(Scalar::from_canonical_bytes(
+            data[4..36].try_into().unwrap(),
+        ));
+        let commitments = get_points(&data[36..]);
+        data.zeroize();
+        let secret = secret.ok_or("invalid scalar")?;
+        let commitments = commitments?;
+        if commitments.is_empty() {
+            return Err("invalid data length");
+        }
+        Self::new(index, secret, CommitteeRecipient { commitments })
+    }
+}
+
+impl Drop for CommitteeIdentity {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+/// What a member keeps between dealing and revealing during key generation:
+/// the coefficients of the polynomial it dealt.
+pub struct CommitteeDealing {
+    pub dealer: u32,
+    coeffs: Vec<Scalar>,
+}
+
+impl CommitteeDealing {
+    pub(crate) fn new(dealer: u32, coeffs: Vec<Scalar>) -> Self {
+        CommitteeDealing { dealer, coeffs }
+    }
+
+    pub(crate) fn coeffs(&self) -> &[Scalar] {
+        &self.coeffs
+    }
+
+    pub fn to_bech32(&self) -> SecretString {
+        let hrp = bech32::Hrp::parse(DEALING_HRP).unwrap();
+        let mut buf = self.dealer.to_le_bytes().to_vec();
+        for c in &self.coeffs {
+            buf.extend_from_slice(c.as_bytes());
+        }
+        let encoded = bech32::encode::<Bech32Long>(hrp, &buf)
+            .unwrap()
+            .to_uppercase();
+        buf.zeroize();
+        SecretString::new(encoded)
+    }
+
+    pub fn from_bech32(s: &str) -> Result<Self, &'static str> {
+        let (hrp, mut data) = decode_long(s)?;
+        if hrp.as_str().to_lowercase() != DEALING_HRP {
+            return Err("invalid HRP");
+        }
+        if data.len() < 36 || !(data.len() - 4).is_multiple_of(32) {
+            return Err("invalid data length");
+        }
+        let dealer = u32::from_le_bytes(data[..4].try_into().unwrap());
+        let coeffs: Option<Vec<_>> = data[4..]
+            .chunks(32)
+            .map(|c| Option::from(Scalar::from_canonical_bytes(c.try_into().unwrap())))
+            .collect();
+        data.zeroize();
+        Ok(CommitteeDealing::new(
+            dealer,
+            coeffs.ok_or("invalid scalar")?,
+        ))
+    }
+}
+
+impl Drop for CommitteeDealing {
+    fn drop(&mut self) {
+        self.coeffs.zeroize();
+    }
+}
+
+/// A dealer's commitments `a_k · B` to the coefficients of its polynomial,
+/// published once every member has received a valid share.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitteeReveal {
+    pub dealer: u32,
+    pub commitments: Vec<RistrettoPoint>,
+}
+
+impl CommitteeReveal {
+    pub fn to_bech32(&self) -> String {
+        let hrp = bech32::Hrp::parse(REVEAL_HRP).unwrap();
+        let buf = put_points(self.dealer.to_le_bytes().to_vec(), &self.commitments);
+        bech32::encode::<Bech32Long>(hrp, &buf)
+            .unwrap()
+            .to_uppercase()
+    }
+
+    pub fn from_bech32(s: &str) -> Result<Self, &'static str> {
+        let (hrp, data) = decode_long(s)?;
+        if hrp.as_str().to_lowercase() != REVEAL_HRP {
+            return Err("invalid HRP");
+        }
+        if data.len() < 4 {
+            return Err("invalid data length");
+        }
+        let commitments = get_points(&data[4..])?;
+        if commitments.is_empty() {
+            return Err("invalid data length");
+        }
+        Ok(CommitteeReveal {
+            dealer: u32::from_le_bytes(data[..4].try_into().unwrap()),
+            commitments,
+        })
+    }
+}
+
+/// A member's partial decryption of a file encrypted to its committee, with a proof that
+/// it was computed with the member's share of the group secret.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartialDecryption {
+    pub index: u32,
+    pub decrypted: RistrettoPoint,
+    pub proof: DleqProof,
+}
+
+impl PartialDecryption {
+    pub fn to_bech32(&self) -> String {
+        let hrp = bech32::Hrp::parse(PARTIAL_HRP).unwrap();
+        let mut buf = self.index.to_le_bytes().to_vec();
+        buf.extend_from_slice(self.decrypted.compress().as_bytes());
+        buf.extend_from_slice(&self.proof.to_bytes());
+        bech32::encode::<Bech32Long>(hrp, &buf)
+            .unwrap()
+            .to_uppercase()
+    }
+
+    pub fn from_bech32(s: &str) -> Result<Self, &'static str> {
+        let (hrp, data) = decode_long(s)?;
+        if hrp.as_str().to_lowercase() != PARTIAL_HRP {
+            return Err("invalid HRP");
+        }
+        if data.len() != 100 {
+            return Err("invalid data length");
+        }
+        Ok(PartialDecryption {
+            index: u32::from_le_bytes(data[..4].try_into().unwrap()),
+            decrypted: get_points(&data[4..36])?[0],
+            proof: DleqProof::from_bytes(&data[36..])?,
+        })
+    }
+}
+
+fn put_points(mut buf: Vec<u8>, points: &[RistrettoPoint]) -> Vec<u8> {
+    for p in points {
+        buf.extend_from_slice(p.compress().as_bytes());
+    }
+    buf
+}
+
+fn get_points(buf: &[u8]) -> Result<Vec<RistrettoPoint>, &'static str> {
+    if !buf.len().is_multiple_of(32) {
+        return Err("invalid data length");
+    }
+    buf.chunks(32)
+        .map(|p| {
+            CompressedRistretto::from_slice(p)
+                .unwrap()
+                .decompress()
+                .ok_or("invalid point")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use age_core::secrecy::ExposeSecret;
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+
+    use super::{CommitteeIdentity, CommitteeRecipient};
+
+    #[test]
+    fn test_round_trip() {
+        // A committee of one member, whose share is the whole secret.
+        let secret = Scalar::random(&mut OsRng);
+        let recipient = CommitteeRecipient {
+            commitments: vec![RISTRETTO_BASEPOINT_POINT * secret],
+        };
+        let encoded = recipient.to_bech32();
+        assert!(encoded.starts_with("age1committee1"));
+        assert_eq!(
+            CommitteeRecipient::from_bech32(&encoded),
+            Ok(recipient.clone())
+        );
+
+        let identity = CommitteeIdentity::new(1, secret, recipient.clone()).unwrap();
+        let encoded = identity.to_bech32();
+        assert!(encoded
+            .expose_secret()
+            .starts_with("AGE-COMMITTEE-SECRET-KEY-1"));
+        let decoded = CommitteeIdentity::from_bech32(encoded.expose_secret()).unwrap();
+        assert_eq!((decoded.index, &decoded.recipient), (1, &recipient));
+
+        assert!(CommitteeIdentity::new(1, secret + Scalar::ONE, recipient).is_err());
+    }
+}
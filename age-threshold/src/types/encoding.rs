@@ -0,0 +1,48 @@
+use bech32::primitives::checksum::Checksum;
+use bech32::primitives::decode::CheckedHrpstring;
+use bech32::Hrp;
+
+/// Bech32 without the length limit, like the encoding age uses for plugin recipients and identities.
+pub(crate) enum Bech32Long {}
+
+impl Checksum for Bech32Long {
+    type MidstateRepr = u32;
+    const CODE_LENGTH: usize = usize::MAX;
+    const CHECKSUM_LENGTH: usize = bech32::Bech32::CHECKSUM_LENGTH;
+    const GENERATOR_SH: [u32; 5] = bech32::Bech32::GENERATOR_SH;
+    const TARGET_RESIDUE: u32 = bech32::Bech32::TARGET_RESIDUE;
+}
+
+/// Decode a string encoded with [`Bech32Long`] into its HRP and data.
+///
+/// Unlike [`bech32::decode`], this accepts strings over the 1023 characters of Bech32.
+pub(crate) fn decode_long(s: &str) -> Result<(Hrp, Vec<u8>), &'static str> {
+    let checked = CheckedHrpstring::new::<Bech32Long>(s).or(Err("invalid bech32"))?;
+    Ok((checked.hrp(), checked.byte_iter().collect()))
+}
+
+/// Append length-prefixed strings to `buf`.
+pub(crate) fn put_strings<'a>(buf: &mut Vec<u8>, strings: impl IntoIterator<Item = &'a str>) {
+    for s in strings {
+        buf.extend_from_slice(&(s.len() as u16).to_le_bytes());
+        buf.extend_from_slice(s.as_bytes());
+    }
+}
+
+/// Read back the strings written by [`put_strings`].
+pub(crate) fn get_strings(mut buf: &[u8]) -> Result<Vec<&str>, &'static str> {
+    let mut v = vec![];
+    while !buf.is_empty() {
+        if buf.len() < 2 {
+            return Err("invalid data length");
+        }
+        let len = u16::from_le_bytes([buf[0], buf[1]]) as usize;
+        buf = &buf[2..];
+        if buf.len() < len {
+            return Err("invalid data length");
+        }
+        v.push(std::str::from_utf8(&buf[..len]).or(Err("invalid string"))?);
+        buf = &buf[len..];
+    }
+    Ok(v)
+}
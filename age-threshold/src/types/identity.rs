@@ -1,5 +1,7 @@
 use std::str::FromStr;
 
+use age_core::secrecy::SecretString;
+
 /// Represents any Age identity, whether native or plugin.
 pub enum AgeIdentity {
     X25519(age::x25519::Identity),
@@ -16,6 +18,13 @@ impl AgeIdentity {
         }
     }
 
+    pub fn to_bech32(&self) -> SecretString {
+        match self {
+            Self::X25519(i) => i.to_string(),
+            Self::Plugin(i) => SecretString::new(i.to_string()),
+        }
+    }
+
     pub fn to_identity<C: age::Callbacks>(
         &self,
         callbacks: C,
@@ -27,7 +36,7 @@ impl AgeIdentity {
                 match age::plugin::IdentityPluginV1::new(
                     // FIXME: use one instance per plugin
                     plugin_name,
-                    &[i.clone()],
+                    std::slice::from_ref(i),
                     callbacks,
                 ) {
                     Err(age::DecryptError::MissingPlugin { binary_name }) => Err(format!(
@@ -0,0 +1,102 @@
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+
+use super::encoding::{decode_long, Bech32Long};
+use super::SecretShare;
+
+/// One holder's part of a split age identity.
+///
+/// Each part is self-contained: besides the holder's share of the key that encrypts the
+/// identity, it carries the commitments to verify it against and the encrypted identity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IdentityShare {
+    pub threshold: u32,
+    pub share: SecretShare,
+    pub commitments: Vec<RistrettoPoint>,
+    pub ciphertext: Vec<u8>,
+}
+
+const IDENTITY_SHARE_HRP: &str = "age-threshold-identity-share-";
+
+impl IdentityShare {
+    pub fn to_bech32(&self) -> String {
+        let hrp = bech32::Hrp::parse(IDENTITY_SHARE_HRP).unwrap();
+        let mut buf = vec![];
+        buf.extend_from_slice(&self.threshold.to_le_bytes());
+        buf.extend_from_slice(&self.share.index.to_le_bytes());
+        buf.extend_from_slice(&self.share.s.to_bytes());
+        buf.extend_from_slice(&self.share.t.to_bytes());
+        for c in &self.commitments {
+            buf.extend_from_slice(c.compress().as_bytes());
+        }
+        buf.extend_from_slice(&self.ciphertext);
+        bech32::encode::<Bech32Long>(hrp, &buf)
+            .unwrap()
+            .to_uppercase()
+    }
+
+    pub fn from_bech32(s: &str) -> Result<Self, &str> {
+        let (hrp, data) = decode_long(s)?;
+        if hrp.as_str().to_lowercase() != IDENTITY_SHARE_HRP {
+            return Err("invalid HRP");
+        }
+        if data.len() < 72 {
+            return Err("invalid data length");
+        }
+        let threshold = u32::from_le_bytes(data[..4].try_into().unwrap());
+        if threshold == 0 || data.len() < 72 + 32 * threshold as usize {
+            return Err("invalid threshold");
+        }
+        let share = SecretShare {
+            index: u32::from_le_bytes(data[4..8].try_into().unwrap()),
+            s: Scalar::from_bytes_mod_order(data[8..40].try_into().unwrap()),
+            t: Scalar::from_bytes_mod_order(data[40..72].try_into().unwrap()),
+        };
+        let mut commitments = vec![];
+        let mut rest = &data[72..];
+        for _ in 0..threshold {
+            let c = CompressedRistretto::from_slice(&rest[..32])
+                .unwrap()
+                .decompress()
+                .ok_or("invalid commitment")?;
+            commitments.push(c);
+            rest = &rest[32..];
+        }
+        Ok(IdentityShare {
+            threshold,
+            share,
+            commitments,
+            ciphertext: rest.to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+
+    use super::IdentityShare;
+    use crate::types::SecretShare;
+
+    #[test]
+    fn test_round_trip() {
+        let example = IdentityShare {
+            threshold: 2,
+            share: SecretShare {
+                index: 3,
+                s: Scalar::from(7u32),
+                t: Scalar::from(11u32),
+            },
+            commitments: vec![
+                RistrettoPoint::hash_from_bytes::<sha2::Sha512>(b"a"),
+                RistrettoPoint::hash_from_bytes::<sha2::Sha512>(b"b"),
+            ],
+            ciphertext: b"sealed identity".to_vec(),
+        };
+        let encoded = example.to_bech32();
+        assert!(encoded.starts_with("AGE-THRESHOLD-IDENTITY-SHARE-1"));
+        assert_eq!(IdentityShare::from_bech32(&encoded), Ok(example));
+        assert!(IdentityShare::from_bech32(&encoded.replace("-IDENTITY", "")).is_err());
+    }
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+/// What is known about the plaintext of a file, encrypted in its header under the file key.
+///
+/// Every field is optional; nothing is recorded unless asked for.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metadata {
+    /// Original file name, without any directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    /// Size of the plaintext in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Modification time, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<u64>,
+    /// MIME type of the plaintext.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Free-form notes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl Metadata {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, &'static str> {
+        serde_json::from_str(s).or(Err("invalid metadata"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metadata;
+
+    #[test]
+    fn test_json() {
+        let metadata = Metadata {
+            filename: Some("blob-7.tar".to_string()),
+            size: Some(1024),
+            ..Default::default()
+        };
+        let json = metadata.to_json();
+        assert_eq!(json, r#"{"filename":"blob-7.tar","size":1024}"#);
+        assert_eq!(Metadata::from_json(&json), Ok(metadata));
+        assert_eq!(Metadata::from_json("{}"), Ok(Metadata::default()));
+        assert!(Metadata::from_json("{\"size\": -1}").is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use curve25519_dalek::scalar::Scalar;
+use once_cell::sync::Lazy;
+
+use super::SecretShare;
+
+/// The SLIP-39 word list: 1024 words, each unique in its first four letters.
+static WORDLIST: Lazy<Vec<&'static str>> =
+    Lazy::new(|| include_str!("wordlist.txt").lines().collect());
+
+const CUSTOMIZATION: &[u8] = b"age-threshold";
+const RADIX_BITS: u32 = 10;
+const METADATA_WORDS: usize = 3;
+const VALUE_WORDS: usize = 52;
+const CHECKSUM_WORDS: usize = 3;
+pub const MNEMONIC_WORDS: usize = METADATA_WORDS + VALUE_WORDS + CHECKSUM_WORDS;
+
+/// A share written as words for safekeeping on paper, in the style of SLIP-39.
+///
+/// Besides the share, the words carry an identifier common to all shares of the same
+/// sharing and its threshold, so that shares from different files are not mixed up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShareMnemonic {
+    /// 15-bit identifier of the sharing.
+    pub identifier: u16,
+    /// Between 1 and 32.
+    pub threshold: u32,
+    /// The share index must be below 1024.
+    pub share: SecretShare,
+}
+
+impl ShareMnemonic {
+    pub fn to_words(&self) -> Result<String, &'static str> {
+        if self.identifier >= 1 << 15 {
+            return Err("invalid identifier");
+        }
+        if self.threshold == 0 || self.threshold > 32 {
+            return Err("threshold too large for a mnemonic");
+        }
+        if self.share.index >= 1 << RADIX_BITS {
+            return Err("share index too large for a mnemonic");
+        }
+
+        let mut data = vec![
+            self.identifier >> 5,
+            (self.identifier & 31) << 5 | (self.threshold - 1) as u16,
+            self.share.index as u16,
+        ];
+        // 512 bits of value, left-padded to 520.
+        let mut value = vec![0u8];
+        value.extend_from_slice(&self.share.s.to_bytes());
+        value.extend_from_slice(&self.share.t.to_bytes());
+        data.extend(to_words(&value));
+        data.extend(checksum(&data));
+        Ok(data
+            .iter()
+            .map(|&w| WORDLIST[w as usize])
+            .collect::<Vec<_>>()
+            .join(" "))
+    }
+
+    /// Parse a mnemonic. Words may be abbreviated to their first four letters.
+    pub fn from_words(s: &str) -> Result<Self, &'static str> {
+        let mut data = vec![];
+        for word in s.split_whitespace() {
+            data.push(word_index(&word.to_lowercase()).ok_or("unknown word")?);
+        }
+        if data.len() != MNEMONIC_WORDS {
+            return Err("invalid mnemonic length");
+        }
+        if polymod(&data) != 1 {
+            return Err("invalid checksum");
+        }
+
+        let value = from_words(&data[METADATA_WORDS..METADATA_WORDS + VALUE_WORDS]);
+        if value[0] != 0 {
+            return Err("invalid padding");
+        }
+        Ok(ShareMnemonic {
+            identifier: data[0] << 5 | data[1] >> 5,
+            threshold: (data[1] & 31) as u32 + 1,
+            share: SecretShare {
+                index: data[2] as u32,
+                s: Scalar::from_bytes_mod_order(value[1..33].try_into().unwrap()),
+                t: Scalar::from_bytes_mod_order(value[33..].try_into().unwrap()),
+            },
+        })
+    }
+}
+
+fn word_index(word: &str) -> Option<u16> {
+    let i = match WORDLIST.binary_search(&word) {
+        Ok(i) => i,
+        Err(i) if word.len() >= 4 && WORDLIST.get(i)?.starts_with(word) => i,
+        Err(_) => return None,
+    };
+    Some(i as u16)
+}
+
+/// Split big-endian bytes into 10-bit words. The bit length must be a multiple of 10.
+fn to_words(bytes: &[u8]) -> Vec<u16> {
+    let mut words = vec![];
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for &b in bytes {
+        acc = acc << 8 | b as u32;
+        bits += 8;
+        while bits >= RADIX_BITS {
+            bits -= RADIX_BITS;
+            words.push((acc >> bits) as u16 & 1023);
+        }
+    }
+    words
+}
+
+fn from_words(words: &[u16]) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for &w in words {
+        acc = acc << RADIX_BITS | w as u32;
+        bits += RADIX_BITS;
+        while bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+        }
+    }
+    bytes
+}
+
+/// The RS1024 checksum of SLIP-39, with its own customization string.
+fn polymod(data: &[u16]) -> u32 {
+    const GEN: [u32; 10] = [
+        0xe0e040, 0x1c1c080, 0x3838100, 0x7070200, 0xe0e0009, 0x1c0c2412, 0x38086c24, 0x3090fc48,
+        0x21b1f890, 0x3f3f120,
+    ];
+    let mut chk = 1u32;
+    let values = CUSTOMIZATION
+        .iter()
+        .map(|&c| c as u16)
+        .chain(data.iter().copied());
+    for v in values {
+        let b = chk >> 20;
+        chk = (chk & 0xfffff) << 10 ^ v as u32;
+        for (i, g) in GEN.iter().enumerate() {
+            if (b >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+fn checksum(data: &[u16]) -> [u16; CHECKSUM_WORDS] {
+    let mut padded = data.to_vec();
+    padded.extend([0; CHECKSUM_WORDS]);
+    let chk = polymod(&padded) ^ 1;
+    [
+        (chk >> 20) as u16 & 1023,
+        (chk >> 10) as u16 & 1023,
+        chk as u16 & 1023,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use curve25519_dalek::scalar::Scalar;
+
+    use super::{ShareMnemonic, MNEMONIC_WORDS, WORDLIST};
+    use crate::types::SecretShare;
+
+    fn example() -> ShareMnemonic {
+        ShareMnemonic {
+            identifier: 0x5a5a,
+            threshold: 3,
+            share: SecretShare {
+                index: 1000,
+                s: Scalar::from(123456789u64),
+                t: -Scalar::ONE,
+            },
+        }
+    }
+
+    #[test]
+    fn test_wordlist() {
+        assert_eq!(WORDLIST.len(), 1024);
+        assert!(WORDLIST.windows(2).all(|w| w[0][..4] < w[1][..4]));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let words = example().to_words().unwrap();
+        assert_eq!(words.split(' ').count(), MNEMONIC_WORDS);
+        assert_eq!(ShareMnemonic::from_words(&words), Ok(example()));
+
+        let abbreviated: Vec<_> = words.split(' ').map(|w| &w[..w.len().min(4)]).collect();
+        assert_eq!(
+            ShareMnemonic::from_words(&abbreviated.join(" ").to_uppercase()),
+            Ok(example())
+        );
+    }
+
+    #[test]
+    fn test_checksum() {
+        let words = example().to_words().unwrap();
+        let mut words: Vec<_> = words.split(' ').collect();
+        words[20] = if words[20] == "academic" {
+            "acid"
+        } else {
+            "academic"
+        };
+        assert_eq!(
+            ShareMnemonic::from_words(&words.join(" ")),
+            Err("invalid checksum")
+        );
+        assert!(ShareMnemonic::from_words(&words[1..].join(" ")).is_err());
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut m = example();
+        m.share.index = 1024;
+        assert!(m.to_words().is_err());
+        let mut m = example();
+        m.threshold = 33;
+        assert!(m.to_words().is_err());
+    }
+}
@@ -0,0 +1,50 @@
+/// Padding of the plaintext of a file, so that its ciphertext does not reveal its exact length.
+///
+/// Plaintexts are padded to a Padmé length, which leaks at most O(log log L) bits of a length L,
+/// after first padding them to `min_size`, which hides the length of shorter plaintexts entirely.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Padding {
+    pub min_size: u64,
+}
+
+impl Padding {
+    /// The length to pad `len` bytes to.
+    pub fn padded_len(&self, len: u64) -> u64 {
+        padme(len.max(self.min_size))
+    }
+}
+
+/// Round `len` up so that only its top O(log log len) bits may be set, as in Padmé
+/// (Nikitin et al., "Reducing Metadata Leakage from Encrypted Files and Communication with PURBs").
+fn padme(len: u64) -> u64 {
+    if len < 2 {
+        return len;
+    }
+    let e = len.ilog2();
+    let s = e.ilog2() + 1;
+    let mask = (1 << (e - s)) - 1;
+    (len + mask) & !mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Padding;
+
+    #[test]
+    fn test_padme() {
+        let padding = Padding::default();
+        for (len, padded) in [
+            (0, 0),
+            (1, 1),
+            (9, 10),
+            (20, 20),
+            (1000, 1024),
+            (1025, 1088),
+        ] {
+            assert_eq!(padding.padded_len(len), padded);
+        }
+        let padding = Padding { min_size: 4096 };
+        assert_eq!(padding.padded_len(20), 4096);
+        assert_eq!(padding.padded_len(5000), 5120);
+    }
+}
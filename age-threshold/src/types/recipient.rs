@@ -1,3 +1,4 @@
+use std::fmt;
 use std::str::FromStr;
 
 /// Represents any Age recipient, whether native or plugin.
@@ -19,7 +20,7 @@ impl AgeRecipient {
     pub fn to_recipient<C: age::Callbacks>(
         &self,
         callbacks: C,
-    ) -> Result<Box<dyn age::Recipient>, String> {
+    ) -> Result<Box<dyn age::Recipient + Send>, String> {
         match self {
             Self::X25519(r) => Ok(Box::new(r.clone())),
             Self::Plugin(r) => {
@@ -27,7 +28,7 @@ impl AgeRecipient {
                 match age::plugin::RecipientPluginV1::new(
                     // FIXME: use one instance per plugin
                     plugin_name,
-                    &[r.clone()],
+                    std::slice::from_ref(r),
                     &[],
                     callbacks,
                 ) {
@@ -43,6 +44,15 @@ impl AgeRecipient {
     }
 }
 
+impl fmt::Display for AgeRecipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::X25519(r) => r.fmt(f),
+            Self::Plugin(r) => r.fmt(f),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::AgeRecipient;
@@ -0,0 +1,116 @@
+use std::fmt;
+
+use age_core::secrecy::{SecretString, Zeroize};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use rand::rngs::OsRng;
+
+use super::encoding::{decode_long, Bech32Long};
+
+const RECIPIENT_HRP: &str = "age1ristretto";
+const IDENTITY_HRP: &str = "age-ristretto-secret-key-";
+
+/// A native ristretto255 public key, `x · B` for the standard base point `B`.
+///
+/// Shares can be encrypted to these keys in a publicly verifiable way, see [`crate::crypto::pvss`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RistrettoRecipient(pub RistrettoPoint);
+
+impl RistrettoRecipient {
+    pub fn to_bech32(&self) -> String {
+        let hrp = bech32::Hrp::parse(RECIPIENT_HRP).unwrap();
+        bech32::encode::<Bech32Long>(hrp, self.0.compress().as_bytes()).unwrap()
+    }
+
+    pub fn from_bech32(s: &str) -> Result<Self, &'static str> {
+        let (hrp, data) = decode_long(s)?;
+        if hrp.as_str() != RECIPIENT_HRP {
+            return Err("invalid HRP");
+        }
+        let point = CompressedRistretto::from_slice(&data)
+            .or(Err("invalid data length"))?
+            .decompress()
+            .ok_or("invalid point")?;
+        Ok(RistrettoRecipient(point))
+    }
+}
+
+impl fmt::Display for RistrettoRecipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_bech32())
+    }
+}
+
+/// The secret scalar of a [`RistrettoRecipient`].
+#[derive(Clone)]
+pub struct RistrettoIdentity(Scalar);
+
+impl RistrettoIdentity {
+    pub fn generate() -> Self {
+        RistrettoIdentity(Scalar::random(&mut OsRng))
+    }
+
+    pub fn to_public(&self) -> RistrettoRecipient {
+        RistrettoRecipient(RISTRETTO_BASEPOINT_POINT * self.0)
+    }
+
+    pub(crate) fn secret(&self) -> &Scalar {
+        &self.0
+    }
+
+    pub fn to_bech32(&self) -> SecretString {
+        let hrp = bech32::Hrp::parse(IDENTITY_HRP).unwrap();
+        SecretString::new(
+            bech32::encode::<Bech32Long>(hrp, self.0.as_bytes())
+                .unwrap()
+                .to_uppercase(),
+        )
+    }
+
+    pub fn from_bech32(s: &str) -> Result<Self, &'static str> {
+        let (hrp, mut data) = decode_long(s)?;
+        if hrp.as_str().to_lowercase() != IDENTITY_HRP {
+            return Err("invalid HRP");
+        }
+        let bytes: [u8; 32] = data.as_slice().try_into().or(Err("invalid data length"))?;
+        data.zeroize();
+        let scalar = Option::from(Scalar::from_canonical_bytes(bytes)).ok_or("invalid scalar")?;
+        if scalar == Scalar::ZERO {
+            return Err("invalid scalar");
+        }
+        Ok(RistrettoIdentity(scalar))
+    }
+}
+
+impl Drop for RistrettoIdentity {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use age_core::secrecy::ExposeSecret;
+
+    use super::{RistrettoIdentity, RistrettoRecipient};
+
+    #[test]
+    fn test_round_trip() {
+        let identity = RistrettoIdentity::generate();
+        let encoded = identity.to_bech32();
+        assert!(encoded
+            .expose_secret()
+            .starts_with("AGE-RISTRETTO-SECRET-KEY-1"));
+        let decoded = RistrettoIdentity::from_bech32(encoded.expose_secret()).unwrap();
+        assert_eq!(decoded.to_public(), identity.to_public());
+
+        let recipient = identity.to_public().to_bech32();
+        assert!(recipient.starts_with("age1ristretto1"));
+        assert_eq!(
+            RistrettoRecipient::from_bech32(&recipient),
+            Ok(identity.to_public())
+        );
+        assert!(RistrettoRecipient::from_bech32(encoded.expose_secret()).is_err());
+    }
+}
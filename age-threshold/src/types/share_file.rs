@@ -0,0 +1,250 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use serde::{Deserialize, Serialize};
+
+use super::{SecretShare, ShareMnemonic};
+use crate::crypto;
+
+/// A share along with everything needed to know where it belongs and to verify it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShareFile {
+    /// Fingerprint of the header the share belongs to, see [`crypto::header_fingerprint`].
+    pub fingerprint: [u8; 32],
+    pub threshold: u32,
+    pub commitments: Vec<RistrettoPoint>,
+    /// Free-form name of the holder, possibly empty.
+    pub label: String,
+    /// Creation time, in seconds since the Unix epoch.
+    pub created: u64,
+    pub share: SecretShare,
+}
+
+pub const SHARE_ARMOR_BEGIN: &str = "-----BEGIN AGE THRESHOLD SHARE-----";
+pub const SHARE_ARMOR_END: &str = "-----END AGE THRESHOLD SHARE-----";
+const ARMOR_COLUMNS: usize = 64;
+const VERSION: u8 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Json {
+    version: u8,
+    fingerprint: String,
+    threshold: u32,
+    commitments: Vec<String>,
+    label: String,
+    created: u64,
+    index: u32,
+    s: String,
+    t: String,
+}
+
+impl ShareFile {
+    /// Check the fingerprint against the commitments, and the share against the commitments.
+    pub fn verify(&self) -> Result<(), &'static str> {
+        if self.threshold == 0 || self.commitments.len() != self.threshold as usize {
+            return Err("invalid threshold");
+        }
+        if crypto::header_fingerprint(self.threshold, &self.commitments) != self.fingerprint {
+            return Err("fingerprint mismatch");
+        }
+        if !crypto::verify_share(&self.share, &self.commitments) {
+            return Err("invalid share");
+        }
+        Ok(())
+    }
+
+    pub fn to_mnemonic(&self) -> ShareMnemonic {
+        ShareMnemonic {
+            identifier: crypto::sharing_identifier(&self.commitments),
+            threshold: self.threshold,
+            share: self.share.clone(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![VERSION];
+        buf.extend_from_slice(&self.fingerprint);
+        buf.extend_from_slice(&self.threshold.to_le_bytes());
+        buf.extend_from_slice(&self.share.index.to_le_bytes());
+        buf.extend_from_slice(&self.share.s.to_bytes());
+        buf.extend_from_slice(&self.share.t.to_bytes());
+        buf.extend_from_slice(&self.created.to_le_bytes());
+        buf.extend_from_slice(&(self.label.len() as u16).to_le_bytes());
+        buf.extend_from_slice(self.label.as_bytes());
+        for c in &self.commitments {
+            buf.extend_from_slice(c.compress().as_bytes());
+        }
+        buf
+    }
+
+    /// Parse and verify a share file in binary form.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, &'static str> {
+        const FIXED: usize = 1 + 32 + 4 + 4 + 32 + 32 + 8 + 2;
+        if data.len() < FIXED {
+            return Err("invalid data length");
+        }
+        if data[0] != VERSION {
+            return Err("unsupported share file version");
+        }
+        let threshold = u32::from_le_bytes(data[33..37].try_into().unwrap());
+        let label_len = u16::from_le_bytes(data[113..115].try_into().unwrap()) as usize;
+        if data.len() != FIXED + label_len + 32 * threshold as usize {
+            return Err("invalid data length");
+        }
+        let label =
+            std::str::from_utf8(&data[FIXED..FIXED + label_len]).or(Err("invalid label"))?;
+        let share_file = ShareFile {
+            fingerprint: data[1..33].try_into().unwrap(),
+            threshold,
+            commitments: data[FIXED + label_len..]
+                .chunks(32)
+                .map(decompress)
+                .collect::<Result<_, _>>()?,
+            label: label.to_string(),
+            created: u64::from_le_bytes(data[105..113].try_into().unwrap()),
+            share: SecretShare {
+                index: u32::from_le_bytes(data[37..41].try_into().unwrap()),
+                s: Scalar::from_bytes_mod_order(data[41..73].try_into().unwrap()),
+                t: Scalar::from_bytes_mod_order(data[73..105].try_into().unwrap()),
+            },
+        };
+        share_file.verify()?;
+        Ok(share_file)
+    }
+
+    pub fn to_armor(&self) -> String {
+        let encoded = STANDARD.encode(self.to_bytes());
+        let mut s = format!("{}\n", SHARE_ARMOR_BEGIN);
+        for line in encoded.as_bytes().chunks(ARMOR_COLUMNS) {
+            s.push_str(std::str::from_utf8(line).unwrap());
+            s.push('\n');
+        }
+        s.push_str(SHARE_ARMOR_END);
+        s.push('\n');
+        s
+    }
+
+    /// Parse and verify a share file in armored form.
+    pub fn from_armor(s: &str) -> Result<Self, &'static str> {
+        let s = s.trim();
+        let body = s
+            .strip_prefix(SHARE_ARMOR_BEGIN)
+            .and_then(|s| s.strip_suffix(SHARE_ARMOR_END))
+            .ok_or("invalid armor")?;
+        let encoded: String = body.split_whitespace().collect();
+        Self::from_bytes(&STANDARD.decode(encoded).or(Err("invalid base64"))?)
+    }
+
+    pub fn to_json(&self) -> String {
+        let json = Json {
+            version: VERSION,
+            fingerprint: hex::encode(self.fingerprint),
+            threshold: self.threshold,
+            commitments: self
+                .commitments
+                .iter()
+                .map(|c| hex::encode(c.compress().as_bytes()))
+                .collect(),
+            label: self.label.clone(),
+            created: self.created,
+            index: self.share.index,
+            s: hex::encode(self.share.s.as_bytes()),
+            t: hex::encode(self.share.t.as_bytes()),
+        };
+        serde_json::to_string_pretty(&json).unwrap()
+    }
+
+    /// Parse and verify a share file in JSON form.
+    pub fn from_json(s: &str) -> Result<Self, &'static str> {
+        let json: Json = serde_json::from_str(s).or(Err("invalid JSON"))?;
+        if json.version != VERSION {
+            return Err("unsupported share file version");
+        }
+        let mut commitments = vec![];
+        for c in &json.commitments {
+            commitments.push(decompress(&hex::decode(c).or(Err("invalid hex"))?)?);
+        }
+        let share_file = ShareFile {
+            fingerprint: decode_32(&json.fingerprint)?,
+            threshold: json.threshold,
+            commitments,
+            label: json.label,
+            created: json.created,
+            share: SecretShare {
+                index: json.index,
+                s: Scalar::from_bytes_mod_order(decode_32(&json.s)?),
+                t: Scalar::from_bytes_mod_order(decode_32(&json.t)?),
+            },
+        };
+        share_file.verify()?;
+        Ok(share_file)
+    }
+}
+
+fn decompress(bytes: &[u8]) -> Result<RistrettoPoint, &'static str> {
+    CompressedRistretto::from_slice(bytes)
+        .or(Err("invalid commitment"))?
+        .decompress()
+        .ok_or("invalid commitment")
+}
+
+fn decode_32(s: &str) -> Result<[u8; 32], &'static str> {
+    hex::decode(s)
+        .or(Err("invalid hex"))?
+        .try_into()
+        .or(Err("invalid length"))
+}
+
+#[cfg(test)]
+mod tests {
+    use age_core::format::{FileKey, FILE_KEY_BYTES};
+
+    use super::ShareFile;
+    use crate::crypto;
+
+    fn example() -> ShareFile {
+        let (shares, commitments) = crypto::share_secret(&FileKey::from([7; FILE_KEY_BYTES]), 2, 3);
+        ShareFile {
+            fingerprint: crypto::header_fingerprint(2, &commitments),
+            threshold: 2,
+            commitments,
+            label: "alice".to_string(),
+            created: 1700000000,
+            share: shares[1].clone(),
+        }
+    }
+
+    #[test]
+    fn test_armor_round_trip() {
+        let example = example();
+        let armored = example.to_armor();
+        assert!(armored.starts_with("-----BEGIN AGE THRESHOLD SHARE-----\n"));
+        assert!(armored
+            .lines()
+            .all(|l| l.len() <= 64 || l.starts_with("-----")));
+        assert_eq!(ShareFile::from_armor(&armored), Ok(example));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let example = example();
+        assert_eq!(ShareFile::from_json(&example.to_json()), Ok(example));
+    }
+
+    #[test]
+    fn test_verify_on_import() {
+        let mut tampered = example();
+        tampered.share.index = 3;
+        assert_eq!(
+            ShareFile::from_json(&tampered.to_json()),
+            Err("invalid share")
+        );
+
+        let mut tampered = example();
+        tampered.fingerprint[0] ^= 1;
+        assert_eq!(
+            ShareFile::from_armor(&tampered.to_armor()),
+            Err("fingerprint mismatch")
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+
+use super::encoding::{decode_long, Bech32Long};
+use super::SecretShare;
+
+/// One holder's share of a secret of arbitrary length, as produced by `three split`.
+///
+/// The secret is shared one 31-byte chunk at a time; the share holds the holder's share of
+/// every chunk, all at the same index, and the commitments of every chunk.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SplitShare {
+    pub threshold: u32,
+    pub length: u32,
+    pub shares: Vec<SecretShare>,
+    pub commitments: Vec<Vec<RistrettoPoint>>,
+}
+
+const SPLIT_SHARE_HRP: &str = "age-threshold-split-";
+
+impl SplitShare {
+    pub fn index(&self) -> u32 {
+        self.shares[0].index
+    }
+
+    pub fn to_bech32(&self) -> String {
+        let hrp = bech32::Hrp::parse(SPLIT_SHARE_HRP).unwrap();
+        let mut buf = vec![];
+        buf.extend_from_slice(&self.threshold.to_le_bytes());
+        buf.extend_from_slice(&self.length.to_le_bytes());
+        buf.extend_from_slice(&self.index().to_le_bytes());
+        for (share, commitments) in self.shares.iter().zip(self.commitments.iter()) {
+            buf.extend_from_slice(&share.s.to_bytes());
+            buf.extend_from_slice(&share.t.to_bytes());
+            for c in commitments {
+                buf.extend_from_slice(c.compress().as_bytes());
+            }
+        }
+        bech32::encode::<Bech32Long>(hrp, &buf)
+            .unwrap()
+            .to_uppercase()
+    }
+
+    pub fn from_bech32(s: &str) -> Result<Self, &str> {
+        let (hrp, data) = decode_long(s)?;
+        if hrp.as_str().to_lowercase() != SPLIT_SHARE_HRP {
+            return Err("invalid HRP");
+        }
+        if data.len() < 12 {
+            return Err("invalid data length");
+        }
+        let threshold = u32::from_le_bytes(data[..4].try_into().unwrap());
+        let length = u32::from_le_bytes(data[4..8].try_into().unwrap());
+        let index = u32::from_le_bytes(data[8..12].try_into().unwrap());
+        if threshold == 0 {
+            return Err("invalid threshold");
+        }
+        let chunk_len = 64 + 32 * threshold as usize;
+        let chunks = (length as usize).div_ceil(31);
+        if chunks == 0 || data.len() != 12 + chunks * chunk_len {
+            return Err("invalid data length");
+        }
+
+        let mut shares = vec![];
+        let mut commitments = vec![];
+        for chunk in data[12..].chunks(chunk_len) {
+            shares.push(SecretShare {
+                index,
+                s: Scalar::from_bytes_mod_order(chunk[..32].try_into().unwrap()),
+                t: Scalar::from_bytes_mod_order(chunk[32..64].try_into().unwrap()),
+            });
+            let mut c = vec![];
+            for point in chunk[64..].chunks(32) {
+                c.push(
+                    CompressedRistretto::from_slice(point)
+                        .unwrap()
+                        .decompress()
+                        .ok_or("invalid commitment")?,
+                );
+            }
+            commitments.push(c);
+        }
+        Ok(SplitShare {
+            threshold,
+            length,
+            shares,
+            commitments,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+
+    use super::SplitShare;
+    use crate::types::SecretShare;
+
+    #[test]
+    fn test_round_trip() {
+        let share = |s: u32| SecretShare {
+            index: 4,
+            s: Scalar::from(s),
+            t: Scalar::from(s + 1),
+        };
+        let point = |s: &[u8]| RistrettoPoint::hash_from_bytes::<sha2::Sha512>(s);
+        let example = SplitShare {
+            threshold: 2,
+            length: 40,
+            shares: vec![share(1), share(3)],
+            commitments: vec![
+                vec![point(b"a"), point(b"b")],
+                vec![point(b"c"), point(b"d")],
+            ],
+        };
+        let encoded = example.to_bech32();
+        assert!(encoded.starts_with("AGE-THRESHOLD-SPLIT-1"));
+        assert_eq!(SplitShare::from_bech32(&encoded), Ok(example.clone()));
+
+        let truncated = SplitShare {
+            length: 20,
+            ..example
+        };
+        assert!(SplitShare::from_bech32(&truncated.to_bech32()).is_err());
+    }
+}
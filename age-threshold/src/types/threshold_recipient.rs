@@ -0,0 +1,187 @@
+use age_core::secrecy::{ExposeSecret, SecretString};
+
+use super::encoding::{decode_long, get_strings, put_strings, Bech32Long};
+use super::{AgeIdentity, AgeRecipient};
+
+/// A threshold and its member recipients, encoded as a single age plugin recipient.
+#[derive(Clone)]
+pub struct ThresholdRecipient {
+    pub threshold: u32,
+    pub recipients: Vec<AgeRecipient>,
+}
+
+/// A set of member identities, encoded as a single age plugin identity.
+pub struct ThresholdIdentity {
+    pub identities: Vec<AgeIdentity>,
+}
+
+pub const PLUGIN_NAME: &str = "threshold";
+const THRESHOLD_RECIPIENT_HRP: &str = "age1threshold";
+const THRESHOLD_IDENTITY_HRP: &str = "age-plugin-threshold-";
+
+impl ThresholdRecipient {
+    pub fn to_bech32(&self) -> String {
+        let hrp = bech32::Hrp::parse(THRESHOLD_RECIPIENT_HRP).unwrap();
+        bech32::encode::<Bech32Long>(hrp, &self.to_bytes()).unwrap()
+    }
+
+    pub fn from_bech32(s: &str) -> Result<Self, &str> {
+        let (hrp, data) = decode_long(s)?;
+        if hrp.as_str().to_lowercase() != THRESHOLD_RECIPIENT_HRP {
+            return Err("invalid HRP");
+        }
+        Self::from_bytes(&data)
+    }
+
+    /// The payload of the bech32 encoding, as handed to the plugin by age.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = self.threshold.to_le_bytes().to_vec();
+        let recipients: Vec<_> = self.recipients.iter().map(|r| r.to_string()).collect();
+        put_strings(&mut buf, recipients.iter().map(|r| r.as_str()));
+        buf
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, &'static str> {
+        if data.len() < 4 {
+            return Err("invalid data length");
+        }
+        let threshold = u32::from_le_bytes(data[..4].try_into().unwrap());
+        let mut recipients = vec![];
+        for r in get_strings(&data[4..])? {
+            recipients.push(AgeRecipient::from_bech32(r).or(Err("invalid member recipient"))?);
+        }
+        if threshold == 0 || threshold as usize > recipients.len() {
+            return Err("invalid threshold");
+        }
+        Ok(ThresholdRecipient {
+            threshold,
+            recipients,
+        })
+    }
+}
+
+impl ThresholdIdentity {
+    pub fn to_bech32(&self) -> SecretString {
+        let hrp = bech32::Hrp::parse(THRESHOLD_IDENTITY_HRP).unwrap();
+        let identities: Vec<_> = self.identities.iter().map(|i| i.to_bech32()).collect();
+        let mut buf = vec![];
+        put_strings(
+            &mut buf,
+            identities.iter().map(|i| i.expose_secret().as_str()),
+        );
+        SecretString::new(
+            bech32::encode::<Bech32Long>(hrp, &buf)
+                .unwrap()
+                .to_uppercase(),
+        )
+    }
+
+    pub fn from_bech32(s: &str) -> Result<Self, &str> {
+        let (hrp, data) = decode_long(s)?;
+        if hrp.as_str().to_lowercase() != THRESHOLD_IDENTITY_HRP {
+            return Err("invalid HRP");
+        }
+        Self::from_bytes(&data)
+    }
+
+    /// The payload of the bech32 encoding, as handed to the plugin by age.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, &'static str> {
+        let mut identities = vec![];
+        for i in get_strings(data)? {
+            identities.push(AgeIdentity::from_bech32(i).or(Err("invalid member identity"))?);
+        }
+        Ok(ThresholdIdentity { identities })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ThresholdIdentity, ThresholdRecipient};
+    use crate::types::{AgeIdentity, AgeRecipient};
+    use age_core::secrecy::ExposeSecret;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_recipient_round_trip() {
+        let members = [
+            "age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p",
+            "age1yubikey1q2w7u3vpya839jxxuq8g0sedh3d740d4xvn639sqhr95ejj8vu3hyfumptt",
+        ];
+        let example = ThresholdRecipient {
+            threshold: 2,
+            recipients: members
+                .iter()
+                .map(|r| AgeRecipient::from_bech32(r).unwrap())
+                .collect(),
+        };
+        let encoded = example.to_bech32();
+        assert!(encoded.starts_with("age1threshold1"));
+        // age itself sees a recipient for the threshold plugin.
+        assert_eq!(
+            age::plugin::Recipient::from_str(&encoded).unwrap().plugin(),
+            "threshold"
+        );
+
+        let decoded = ThresholdRecipient::from_bech32(&encoded).unwrap();
+        assert_eq!(decoded.threshold, 2);
+        let decoded: Vec<_> = decoded.recipients.iter().map(|r| r.to_string()).collect();
+        assert_eq!(decoded, members);
+    }
+
+    #[test]
+    fn test_long_recipient_round_trip() {
+        // Over the 1023 characters that plain Bech32 decoders accept.
+        let members: Vec<_> = (0..20)
+            .map(|_| age::x25519::Identity::generate().to_public().to_string())
+            .collect();
+        let example = ThresholdRecipient {
+            threshold: 11,
+            recipients: members
+                .iter()
+                .map(|r| AgeRecipient::from_bech32(r).unwrap())
+                .collect(),
+        };
+        let encoded = example.to_bech32();
+        assert!(encoded.len() > 1023);
+
+        let decoded = ThresholdRecipient::from_bech32(&encoded).unwrap();
+        assert_eq!(decoded.threshold, 11);
+        let decoded: Vec<_> = decoded.recipients.iter().map(|r| r.to_string()).collect();
+        assert_eq!(decoded, members);
+        assert!(ThresholdRecipient::from_bech32(&encoded.to_uppercase()).is_ok());
+    }
+
+    #[test]
+    fn test_recipient_invalid_threshold() {
+        let example = ThresholdRecipient {
+            threshold: 2,
+            recipients: vec![AgeRecipient::from_bech32(
+                "age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p",
+            )
+            .unwrap()],
+        };
+        assert!(ThresholdRecipient::from_bech32(&example.to_bech32()).is_err());
+    }
+
+    #[test]
+    fn test_identity_round_trip() {
+        let member = "AGE-SECRET-KEY-1QL3Z7HJY54PW3HYWW5AYYFG7ZQGVC7W3J2ELW8ZMRJ2KG5SFN9AQGHWZHJ";
+        let example = ThresholdIdentity {
+            identities: vec![AgeIdentity::from_bech32(member).unwrap()],
+        };
+        let encoded = example.to_bech32();
+        assert!(encoded
+            .expose_secret()
+            .starts_with("AGE-PLUGIN-THRESHOLD-1"));
+        assert_eq!(
+            age::plugin::Identity::from_str(encoded.expose_secret())
+                .unwrap()
+                .plugin(),
+            "threshold"
+        );
+
+        let decoded = ThresholdIdentity::from_bech32(encoded.expose_secret()).unwrap();
+        assert_eq!(decoded.identities.len(), 1);
+        assert_eq!(decoded.identities[0].to_bech32().expose_secret(), member);
+    }
+}
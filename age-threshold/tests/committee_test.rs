@@ -0,0 +1,113 @@
+use std::io;
+
+use age_threshold::types::{CommitteeHeader, CommitteeIdentity, Deal};
+use age_threshold::{
+    committee_deal, committee_finish, committee_reveal, decrypt_committee, encrypt_committee,
+    partial_decrypt, read_deal,
+};
+
+mod common;
+use common::keypair;
+
+/// Run key generation for `n` members with threshold `t`.
+fn keygen(t: u32, n: usize) -> io::Result<Vec<CommitteeIdentity>> {
+    let members: Vec<_> = (0..n).map(|_| keypair()).collect();
+    let recipients: Vec<_> = members.iter().map(|(_, r)| r.clone()).collect();
+
+    let mut dealings = vec![];
+    let mut deals = vec![];
+    for i in 1..=n as u32 {
+        let mut deal = vec![];
+        dealings.push(committee_deal(i, &recipients, t, &mut deal)?);
+        deals.push(deal);
+    }
+    let read_deals =
+        || -> io::Result<Vec<Deal>> { deals.iter().map(|d| read_deal(&mut &d[..])).collect() };
+
+    let mut reveals = vec![];
+    for ((identity, _), dealing) in members.iter().zip(&dealings) {
+        let identities = std::slice::from_ref(identity);
+        reveals.push(committee_reveal(identities, dealing, &read_deals()?)?);
+    }
+    let mut committee = vec![];
+    for (identity, _) in &members {
+        let identities = std::slice::from_ref(identity);
+        committee.push(committee_finish(identities, &read_deals()?, &reveals)?);
+    }
+    Ok(committee)
+}
+
+#[test]
+fn keygen_encrypt_decrypt() -> io::Result<()> {
+    let committee = keygen(2, 3)?;
+    let recipient = committee[0].recipient.clone();
+    assert!(committee.iter().all(|m| m.recipient == recipient));
+    let indices: Vec<_> = committee.iter().map(|m| m.index).collect();
+    assert_eq!(indices, [1, 2, 3]);
+
+    let mut enc = vec![];
+    encrypt_committee(&recipient, &mut &b"group secret"[..], &mut enc)?;
+    assert!(enc.starts_with(b"bbjubjub.fr/age-threshold/committee/v0\n"));
+
+    let mut fingerprints = vec![];
+    let mut approve = |header: &CommitteeHeader| {
+        fingerprints.push(header.fingerprint());
+        Ok(())
+    };
+    let partials = [
+        partial_decrypt(&committee[2], &mut approve, &mut &enc[..])?,
+        partial_decrypt(&committee[0], &mut approve, &mut &enc[..])?,
+    ];
+    assert_eq!(fingerprints[0], fingerprints[1]);
+    let decline = |_: &CommitteeHeader| Err(io::Error::other("release declined"));
+    assert!(partial_decrypt(&committee[1], decline, &mut &enc[..]).is_err());
+    let mut buf = vec![];
+    decrypt_committee(&partials, &mut &enc[..], &mut buf)?;
+    assert_eq!(&buf[..], b"group secret");
+
+    // The same member twice is not enough.
+    let err = decrypt_committee(
+        &[partials[0].clone(), partials[0].clone()],
+        &mut &enc[..],
+        &mut vec![],
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "not enough partial decryptions");
+
+    // A partial decryption claiming another member's index is caught.
+    let mut forged = partials[1].clone();
+    forged.index = 2;
+    let err =
+        decrypt_committee(&[partials[0].clone(), forged], &mut &enc[..], &mut vec![]).unwrap_err();
+    assert_eq!(err.to_string(), "invalid partial decryption from member 2");
+    Ok(())
+}
+
+#[test]
+fn reject_inconsistent_reveal() -> io::Result<()> {
+    let members: Vec<_> = (0..2).map(|_| keypair()).collect();
+    let recipients: Vec<_> = members.iter().map(|(_, r)| r.clone()).collect();
+    let mut deals = [vec![], vec![]];
+    let first = committee_deal(1, &recipients, 2, &mut deals[0])?;
+    let second = committee_deal(2, &recipients, 2, &mut deals[1])?;
+    let parsed =
+        || -> io::Result<Vec<Deal>> { deals.iter().map(|d| read_deal(&mut &d[..])).collect() };
+
+    let identities = std::slice::from_ref(&members[0].0);
+    assert!(committee_reveal(identities, &second, &parsed()?).is_err());
+    let mut reveals = vec![
+        committee_reveal(identities, &first, &parsed()?)?,
+        committee_reveal(std::slice::from_ref(&members[1].0), &second, &parsed()?)?,
+    ];
+    // Revealing commitments to another polynomial than the one dealt is caught.
+    reveals[1].commitments = reveals[0].commitments.clone();
+    let err = committee_finish(identities, &parsed()?, &reveals)
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "inconsistent reveal from member 2");
+    let err = committee_finish(identities, &parsed()?, &reveals[..1])
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "missing reveal from member 2");
+    Ok(())
+}
@@ -0,0 +1,29 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use age_threshold::types::{AgeIdentity, AgeRecipient, EncryptOptions};
+use std::io;
+
+/// A fresh X25519 identity and its recipient.
+pub fn keypair() -> (AgeIdentity, AgeRecipient) {
+    let id = age::x25519::Identity::generate();
+    let r = AgeRecipient::X25519(id.to_public());
+    (AgeIdentity::X25519(id), r)
+}
+
+/// Encrypt `msg` to a `t`-out-of-`recipients.len()` file.
+pub fn encrypt_with(
+    recipients: &[AgeRecipient],
+    t: u32,
+    opts: &EncryptOptions,
+    msg: &[u8],
+) -> io::Result<Vec<u8>> {
+    let mut enc = vec![];
+    age_threshold::encrypt_with(recipients, t, opts, &mut &msg[..], &mut enc)?;
+    Ok(enc)
+}
+
+/// Encrypt `msg` to a `t`-out-of-`recipients.len()` file with the default options.
+pub fn encrypt(recipients: &[AgeRecipient], t: u32, msg: &[u8]) -> io::Result<Vec<u8>> {
+    encrypt_with(recipients, t, &EncryptOptions::default(), msg)
+}
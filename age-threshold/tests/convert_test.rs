@@ -0,0 +1,175 @@
+use age_core::secrecy::ExposeSecret;
+use age_threshold::types::{AgeIdentity, AgeRecipient, EncryptOptions, HeaderVersion};
+use std::io;
+use std::io::prelude::*;
+
+mod common;
+use common::keypair;
+
+fn payload(file: &[u8], header_end: &[u8]) -> Vec<u8> {
+    let start = file
+        .windows(header_end.len())
+        .position(|w| w == header_end)
+        .unwrap();
+    let start = start + 1 + file[start + 1..].iter().position(|&c| c == b'\n').unwrap() + 1;
+    file[start..].to_vec()
+}
+
+fn age_encrypt(recipient: age::x25519::Recipient, msg: &[u8]) -> Vec<u8> {
+    let mut age_file = vec![];
+    let encryptor = age::Encryptor::with_recipients(vec![Box::new(recipient)]).unwrap();
+    let mut writer = encryptor.wrap_output(&mut age_file).unwrap();
+    writer.write_all(msg).unwrap();
+    writer.finish().unwrap();
+    age_file
+}
+
+#[test]
+fn age_round_trip() -> io::Result<()> {
+    let msg = b"an old age archive";
+    let age_id = age::x25519::Identity::generate();
+    let (id1, r1) = keypair();
+    let (id2, r2) = keypair();
+    let (_, r3) = keypair();
+
+    let age_file = age_encrypt(age_id.to_public(), msg);
+
+    let mut threshold_file = vec![];
+    age_threshold::from_age(
+        &[AgeIdentity::X25519(age_id.clone())],
+        &[r1, r2, r3],
+        2,
+        &mut &age_file[..],
+        &mut threshold_file,
+    )?;
+    assert_eq!(
+        payload(&age_file, b"\n---"),
+        payload(&threshold_file, b"\n---")
+    );
+
+    let ids = [id1, id2];
+    let mut buf = vec![];
+    age_threshold::decrypt(&ids, &mut &threshold_file[..], &mut buf)?;
+    assert_eq!(&buf[..], &msg[..]);
+
+    let new_age_id = age::x25519::Identity::generate();
+    let mut back = vec![];
+    age_threshold::to_age(
+        &ids,
+        &[AgeRecipient::X25519(new_age_id.to_public())],
+        &mut &threshold_file[..],
+        &mut back,
+    )?;
+    assert_eq!(payload(&age_file, b"\n---"), payload(&back, b"\n---"));
+
+    let age::Decryptor::Recipients(decryptor) = age::Decryptor::new(&back[..]).unwrap() else {
+        unreachable!()
+    };
+    let mut reader = decryptor
+        .decrypt(std::iter::once(&new_age_id as &dyn age::Identity))
+        .unwrap();
+    let mut buf = vec![];
+    reader.read_to_end(&mut buf)?;
+    assert_eq!(&buf[..], &msg[..]);
+    Ok(())
+}
+
+#[test]
+fn from_age_tampered_header() -> io::Result<()> {
+    let age_id = age::x25519::Identity::generate();
+    let (_, r1) = keypair();
+
+    let mut age_file = age_encrypt(age_id.to_public(), b"x");
+
+    // Flip a character of the MAC.
+    let mac = age_file.windows(4).position(|w| w == b"--- ").unwrap() + 4;
+    age_file[mac] = if age_file[mac] == b'A' { b'B' } else { b'A' };
+    assert!(age_threshold::from_age(
+        &[AgeIdentity::X25519(age_id)],
+        &[r1],
+        1,
+        &mut &age_file[..],
+        &mut io::sink()
+    )
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn wrap_unwrap_file_key() -> io::Result<()> {
+    let (id1, r1) = keypair();
+    let (id2, r2) = keypair();
+    let (_, r3) = keypair();
+
+    let file_key = age_core::format::FileKey::from([7; 16]);
+    let header = age_threshold::wrap_file_key(&file_key, &[r1, r2, r3], 2)?;
+    assert_eq!(
+        age_threshold::unwrap_file_key(std::slice::from_ref(&id1), &header)?
+            .map(|fk| *fk.expose_secret()),
+        None
+    );
+    assert_eq!(
+        age_threshold::unwrap_file_key(&[id1, id2], &header)?.map(|fk| *fk.expose_secret()),
+        Some([7; 16])
+    );
+    Ok(())
+}
+
+#[test]
+fn age_payload_over_several_chunks() -> io::Result<()> {
+    let msg: Vec<u8> = (0..150_000u32).map(|i| (i % 251) as u8).collect();
+    let age_id = age::x25519::Identity::generate();
+    let (id1, r1) = keypair();
+    let (_, r2) = keypair();
+
+    let age_file = age_encrypt(age_id.to_public(), &msg);
+    let mut threshold_file = vec![];
+    age_threshold::from_age(
+        &[AgeIdentity::X25519(age_id)],
+        &[r1, r2],
+        1,
+        &mut &age_file[..],
+        &mut threshold_file,
+    )?;
+
+    let mut buf = vec![];
+    age_threshold::decrypt(&[id1], &mut &threshold_file[..], &mut buf)?;
+    assert_eq!(buf, msg);
+    Ok(())
+}
+
+#[test]
+fn little_endian_payload_to_age() -> io::Result<()> {
+    let msg: Vec<u8> = (0..150_000u32).map(|i| (i % 251) as u8).collect();
+    let (id1, r1) = keypair();
+    let opts = EncryptOptions {
+        version: HeaderVersion::V0,
+        ..Default::default()
+    };
+    let mut threshold_file = vec![];
+    age_threshold::encrypt_with(&[r1], 1, &opts, &mut &msg[..], &mut threshold_file)?;
+
+    let age_id = age::x25519::Identity::generate();
+    let mut age_file = vec![];
+    age_threshold::to_age(
+        &[id1],
+        &[AgeRecipient::X25519(age_id.to_public())],
+        &mut &threshold_file[..],
+        &mut age_file,
+    )?;
+    assert_ne!(
+        payload(&threshold_file, b"\n---")[..16],
+        payload(&age_file, b"\n---")[..16]
+    );
+
+    let age::Decryptor::Recipients(decryptor) = age::Decryptor::new(&age_file[..]).unwrap() else {
+        unreachable!()
+    };
+    let mut reader = decryptor
+        .decrypt(std::iter::once(&age_id as &dyn age::Identity))
+        .unwrap();
+    let mut buf = vec![];
+    reader.read_to_end(&mut buf)?;
+    assert_eq!(buf, msg);
+    Ok(())
+}
@@ -60,3 +60,70 @@ fn decrypt_sample_pq() -> io::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn decrypt_sample_hash() -> io::Result<()> {
+    let msg = testdata::Data::get("2outof3_hash/message").unwrap();
+    let enc_msg = testdata::Data::get("2outof3_hash/message.age").unwrap();
+    let key1 = testdata::Data::get("2outof3_hash/key1").unwrap();
+    let key3 = testdata::Data::get("2outof3_hash/key3").unwrap();
+
+    let age::IdentityFileEntry::Native(ref id) =
+        age::IdentityFile::from_buffer(io::Cursor::new(&key1.data))?.into_identities()[0]
+    else {
+        unreachable!()
+    };
+    let id1 =
+        age_threshold::types::AgeIdentity::from_bech32(id.to_string().expose_secret()).unwrap();
+    let err = age_threshold::decrypt(
+        std::slice::from_ref(&id1),
+        &mut io::Cursor::new(&enc_msg.data),
+        &mut io::sink(),
+    );
+    assert!(err.is_err());
+
+    let age::IdentityFileEntry::Native(ref id) =
+        age::IdentityFile::from_buffer(io::Cursor::new(&key3.data))?.into_identities()[0]
+    else {
+        unreachable!()
+    };
+    let id3 =
+        age_threshold::types::AgeIdentity::from_bech32(id.to_string().expose_secret()).unwrap();
+    let mut buf = io::Cursor::new(vec![]);
+    age_threshold::decrypt(&[id1, id3], &mut io::Cursor::new(&enc_msg.data), &mut buf)?;
+    assert_eq!(&buf.get_ref()[..], &msg.data[..]);
+
+    Ok(())
+}
+
+#[test]
+fn decrypt_sample_little_endian() -> io::Result<()> {
+    let msg = testdata::Data::get("2outof3_le/message").unwrap();
+    let enc_msg = testdata::Data::get("2outof3_le/message.age").unwrap();
+    let key1 = testdata::Data::get("2outof3_le/key1").unwrap();
+    let key3 = testdata::Data::get("2outof3_le/key3").unwrap();
+
+    let mut ids = vec![];
+    for key in [key1, key3] {
+        let age::IdentityFileEntry::Native(ref id) =
+            age::IdentityFile::from_buffer(io::Cursor::new(&key.data))?.into_identities()[0]
+        else {
+            unreachable!()
+        };
+        ids.push(
+            age_threshold::types::AgeIdentity::from_bech32(id.to_string().expose_secret()).unwrap(),
+        );
+    }
+    let mut buf = io::Cursor::new(vec![]);
+    age_threshold::decrypt(&ids, &mut io::Cursor::new(&enc_msg.data), &mut buf)?;
+    assert_eq!(&buf.get_ref()[..], &msg.data[..]);
+
+    // A corrupted chunk is reported by number.
+    let mut tampered = enc_msg.data.to_vec();
+    *tampered.last_mut().unwrap() ^= 1;
+    let err =
+        age_threshold::decrypt(&ids, &mut io::Cursor::new(&tampered), &mut io::sink()).unwrap_err();
+    assert_eq!(err.to_string(), "payload chunk 1 failed to authenticate");
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use age_threshold::crypto::{enrollment_pieces, sum_shares};
+use std::io;
+
+mod common;
+use common::keypair;
+
+#[test]
+fn enroll_new_holder() -> io::Result<()> {
+    let msg = b"welcome aboard";
+    let (id1, r1) = keypair();
+    let (_, r2) = keypair();
+    let (id3, r3) = keypair();
+    let (id4, r4) = keypair();
+
+    let mut enc = vec![];
+    age_threshold::encrypt(&[r1, r2, r3], 2, &mut &msg[..], &mut enc)?;
+
+    let helpers = [1, 3];
+    let mut held = vec![vec![]; helpers.len()];
+    for id in [&id1, &id3] {
+        let mut shares = age_threshold::decrypt_shares(std::slice::from_ref(id), &mut &enc[..])?;
+        let pieces = enrollment_pieces(&shares.remove(0), &helpers, 4).map_err(io::Error::other)?;
+        for (j, piece) in pieces.into_iter().enumerate() {
+            held[j].push(piece);
+        }
+    }
+    let sums = held
+        .iter()
+        .map(|v| sum_shares(v))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io::Error::other)?;
+    let new_share = sum_shares(&sums).map_err(io::Error::other)?;
+
+    let mut enrolled = vec![];
+    age_threshold::enroll(
+        &new_share,
+        std::slice::from_ref(&r4),
+        &mut &enc[..],
+        &mut enrolled,
+    )?;
+    // Enrolling the same index twice is refused.
+    assert!(age_threshold::enroll(&new_share, &[r4], &mut &enrolled[..], &mut io::sink()).is_err());
+
+    let mut buf = vec![];
+    age_threshold::decrypt(&[id4, id1], &mut &enrolled[..], &mut buf)?;
+    assert_eq!(&buf[..], &msg[..]);
+    Ok(())
+}
+
+#[test]
+fn enroll_inconsistent_share() -> io::Result<()> {
+    let (id1, r1) = keypair();
+    let (_, r2) = keypair();
+
+    let mut enc = vec![];
+    age_threshold::encrypt(&[r1, r2.clone()], 2, &mut &b"x"[..], &mut enc)?;
+    let mut share = age_threshold::decrypt_shares(&[id1], &mut &enc[..])?.remove(0);
+    share.index = 3;
+    assert!(age_threshold::enroll(&share, &[r2], &mut &enc[..], &mut io::sink()).is_err());
+    Ok(())
+}
+
+#[test]
+fn sealed_piece() -> io::Result<()> {
+    let (id1, r1) = keypair();
+    let (id2, _) = keypair();
+    let mut enc = vec![];
+    age_threshold::encrypt(std::slice::from_ref(&r1), 1, &mut &b"x"[..], &mut enc)?;
+    let share = age_threshold::decrypt_shares(std::slice::from_ref(&id1), &mut &enc[..])?.remove(0);
+
+    let mut sealed = vec![];
+    age_threshold::seal_piece(&share, &r1, &mut sealed)?;
+    assert!(age_threshold::open_piece(&[id2], &mut &sealed[..]).is_err());
+    assert_eq!(age_threshold::open_piece(&[id1], &mut &sealed[..])?, share);
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use age_threshold::types::{AgeRecipient, CommitmentScheme, EncryptOptions};
+use std::io;
+
+mod common;
+use common::keypair;
+
+fn encrypt_hash(recipients: &[AgeRecipient], t: u32, msg: &[u8]) -> io::Result<Vec<u8>> {
+    let opts = EncryptOptions {
+        scheme: CommitmentScheme::Hash,
+        ..Default::default()
+    };
+    common::encrypt_with(recipients, t, &opts, msg)
+}
+
+#[test]
+fn encrypt_decrypt_hash_commitments() -> io::Result<()> {
+    let msg = b"no discrete logarithms here";
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..3).map(|_| keypair()).unzip();
+    let enc = encrypt_hash(&recipients, 2, msg)?;
+    let header =
+        String::from_utf8_lossy(&enc[..enc.windows(4).position(|w| w == b"---\n").unwrap()]);
+    assert!(header.contains("\n-> hash-commitments "));
+    assert!(!header.contains("\n-> commitments "));
+
+    let mut buf = vec![];
+    age_threshold::decrypt(&ids[1..], &mut &enc[..], &mut buf)?;
+    assert_eq!(&buf[..], &msg[..]);
+
+    let shares = age_threshold::decrypt_shares(&ids[..1], &mut &enc[..])?;
+    assert_eq!(shares.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn reject_tampered_masked_polynomial() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..3).map(|_| keypair()).unzip();
+    let enc = encrypt_hash(&recipients, 2, b"x")?;
+
+    // Swapping the coefficients of the masked polynomial leaves the header well-formed.
+    let mut text = String::from_utf8_lossy(&enc).into_owned();
+    let start = text.find("-> hash-commitments ").unwrap();
+    let end = start + text[start..].find('\n').unwrap();
+    let args: Vec<_> = text[start..end].split(' ').map(String::from).collect();
+    let swapped = format!("-> hash-commitments {} {}", args[3], args[2]);
+    text.replace_range(start..end, &swapped);
+    let payload = enc.len() - enc.windows(4).position(|w| w == b"---\n").unwrap();
+    let mut tampered = text.as_bytes()[..text.find("---\n").unwrap()].to_vec();
+    tampered.extend_from_slice(&enc[enc.len() - payload..]);
+
+    // Shares are bound to the commitments, so this is caught before share verification.
+    let err = age_threshold::decrypt(&ids[..2], &mut &tampered[..], &mut io::sink()).unwrap_err();
+    assert_eq!(err.to_string(), "share decryption failed");
+    Ok(())
+}
+
+#[test]
+fn share_files_need_pedersen_commitments() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let enc = encrypt_hash(&recipients, 2, b"x")?;
+
+    let err = age_threshold::export_shares(&ids[..1], &mut &enc[..]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "not supported for files with hash commitments"
+    );
+    let holders: Vec<_> = (1..).zip(recipients).collect();
+    let err = age_threshold::refresh(&holders, &mut &enc[..], &mut vec![]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "not supported for files with hash commitments"
+    );
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use std::io;
+
+use age_threshold::types::{AgeIdentity, AgeRecipient, IdentityShare};
+use age_threshold::{combine_identity, decrypt, encrypt, split_identity};
+
+#[test]
+fn split_and_decrypt() -> io::Result<()> {
+    let escrowed = age::x25519::Identity::generate();
+    let other = age::x25519::Identity::generate();
+    let recipients = [
+        AgeRecipient::X25519(escrowed.to_public()),
+        AgeRecipient::X25519(other.to_public()),
+    ];
+    let mut enc = vec![];
+    encrypt(&recipients, 1, &mut &b"escrow"[..], &mut enc)?;
+
+    let shares = split_identity(&AgeIdentity::X25519(escrowed), 3, 5)?;
+    assert_eq!(shares.len(), 5);
+    let printed: Vec<_> = shares.iter().map(|s| s.to_bech32()).collect();
+    let parsed: Vec<_> = [&printed[4], &printed[0], &printed[2]]
+        .iter()
+        .map(|s| IdentityShare::from_bech32(s).unwrap())
+        .collect();
+
+    let identity = combine_identity(&parsed)?;
+    let mut buf = vec![];
+    decrypt(&[identity], &mut &enc[..], &mut buf)?;
+    assert_eq!(&buf[..], b"escrow");
+
+    assert!(combine_identity(&parsed[..2]).is_err());
+    let duplicated = [parsed[0].clone(), parsed[0].clone(), parsed[1].clone()];
+    assert!(combine_identity(&duplicated).is_err());
+    Ok(())
+}
+
+#[test]
+fn reject_mixed_shares() -> io::Result<()> {
+    let a = split_identity(
+        &AgeIdentity::X25519(age::x25519::Identity::generate()),
+        2,
+        3,
+    )?;
+    let b = split_identity(
+        &AgeIdentity::X25519(age::x25519::Identity::generate()),
+        2,
+        3,
+    )?;
+    assert!(combine_identity(&[a[0].clone(), b[1].clone()]).is_err());
+
+    let mut tampered = a[1].clone();
+    tampered.share.s += curve25519_dalek::scalar::Scalar::ONE;
+    assert!(combine_identity(&[a[0].clone(), tampered]).is_err());
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use age_threshold::types::AgeRecipient;
+use std::io;
+
+mod common;
+use common::keypair;
+
+fn encrypt(recipients: &[AgeRecipient], msg: &[u8]) -> io::Result<Vec<u8>> {
+    common::encrypt(recipients, 2, msg)
+}
+
+/// The line after the key commitment stanza, which holds its body.
+fn commitment_body(enc: &[u8]) -> std::ops::Range<usize> {
+    let pattern = b"-> crit key-commitment\n";
+    let start = enc
+        .windows(pattern.len())
+        .position(|w| w == pattern)
+        .unwrap()
+        + pattern.len();
+    let end = start + enc[start..].iter().position(|&b| b == b'\n').unwrap();
+    start..end
+}
+
+#[test]
+fn committed_roundtrip() -> io::Result<()> {
+    let msg = b"one plaintext for every quorum";
+    let (mut ids, recipients): (Vec<_>, Vec<_>) = (0..3).map(|_| keypair()).unzip();
+    let enc = encrypt(&recipients, msg)?;
+    commitment_body(&enc);
+
+    for quorum in [&ids[..2], &ids[1..]] {
+        let mut buf = vec![];
+        age_threshold::decrypt(quorum, &mut &enc[..], &mut buf)?;
+        assert_eq!(&buf[..], &msg[..]);
+    }
+
+    // The commitment is carried over when the header is rewritten.
+    let (new_id, new_r) = keypair();
+    let mut rewrapped = vec![];
+    age_threshold::rewrap(&ids[..1], &[new_r], &mut &enc[..], &mut rewrapped)?;
+    assert_eq!(
+        &rewrapped[commitment_body(&rewrapped)],
+        &enc[commitment_body(&enc)]
+    );
+    ids[0] = new_id;
+    let mut buf = vec![];
+    age_threshold::decrypt(&ids[..2], &mut &rewrapped[..], &mut buf)?;
+    assert_eq!(&buf[..], &msg[..]);
+    Ok(())
+}
+
+#[test]
+fn reject_other_file_key() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let enc = encrypt(&recipients, b"x")?;
+    let other = encrypt(&recipients, b"x")?;
+
+    // A header committing to a file key its shares do not reconstruct.
+    let range = commitment_body(&enc);
+    let tampered = [
+        &enc[..range.start],
+        &other[commitment_body(&other)],
+        &enc[range.end..],
+    ]
+    .concat();
+    let mut buf = vec![];
+    let err = age_threshold::decrypt(&ids, &mut &tampered[..], &mut buf).unwrap_err();
+    assert_eq!(err.to_string(), "file key does not match its commitment");
+    assert!(buf.is_empty());
+
+    let header = age_threshold::inspect(&mut &tampered[..])?;
+    assert!(age_threshold::read_metadata(&ids, &header).is_err());
+    Ok(())
+}
+
+#[test]
+fn reject_missing_commitment() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let enc = encrypt(&recipients, b"x")?;
+
+    // Version 1 headers must commit to their file key.
+    let pattern = b"-> crit key-commitment\n";
+    let range = commitment_body(&enc);
+    let stripped = [&enc[..range.start - pattern.len()], &enc[range.end + 1..]].concat();
+    let header = age_threshold::inspect(&mut &stripped[..])?;
+    assert!(header.extensions.is_empty());
+    let err = age_threshold::decrypt(&ids, &mut &stripped[..], &mut io::sink()).unwrap_err();
+    assert_eq!(err.to_string(), "missing key commitment");
+    Ok(())
+}
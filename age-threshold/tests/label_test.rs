@@ -0,0 +1,61 @@
+use age_threshold::types::{AgeRecipient, EncryptOptions};
+use std::io;
+
+mod common;
+use common::keypair;
+
+fn encrypt_labeled(recipients: &[AgeRecipient], msg: &[u8]) -> io::Result<Vec<u8>> {
+    let opts = EncryptOptions {
+        labels: vec![Some("alice".to_string()), None, Some("carol".to_string())],
+        ..Default::default()
+    };
+    common::encrypt_with(recipients, 2, &opts, msg)
+}
+
+#[test]
+fn labels_in_header() -> io::Result<()> {
+    let msg = b"who holds what";
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..3).map(|_| keypair()).unzip();
+    let enc = encrypt_labeled(&recipients, msg)?;
+
+    let header = age_threshold::inspect(&mut &enc[..])?;
+    assert_eq!(header.label(1), Some("alice"));
+    assert_eq!(header.label(2), None);
+    assert_eq!(header.label(3), Some("carol"));
+
+    let mut buf = vec![];
+    age_threshold::decrypt(&ids[1..], &mut &enc[..], &mut buf)?;
+    assert_eq!(&buf[..], &msg[..]);
+
+    let share_files = age_threshold::export_shares(&ids[2..], &mut &enc[..])?;
+    assert_eq!(share_files[0].label, "carol");
+
+    let err = age_threshold::decrypt(&ids[1..2], &mut &enc[..], &mut io::sink()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "not enough shares, missing 1 (alice, unverified), 3 (carol, unverified)"
+    );
+    Ok(())
+}
+
+#[test]
+fn reject_tampered_label() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..3).map(|_| keypair()).unzip();
+    let enc = encrypt_labeled(&recipients, b"x")?;
+
+    // Relabel share 3 as belonging to somebody else.
+    let pos = enc
+        .windows(15)
+        .position(|w| w == b"-> ext label 3\n")
+        .unwrap()
+        + 15;
+    let body = b"Y2Fyb2w\n";
+    assert_eq!(&enc[pos..pos + body.len()], body);
+    let tampered = [&enc[..pos], b"bWFsbG9yeQ\n", &enc[pos + body.len()..]].concat();
+
+    let header = age_threshold::inspect(&mut &tampered[..])?;
+    assert_eq!(header.label(3), Some("mallory"));
+    let err = age_threshold::decrypt(&ids[2..], &mut &tampered[..], &mut io::sink()).unwrap_err();
+    assert_eq!(err.to_string(), "share decryption failed");
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use age_threshold::types::{AgeRecipient, EncryptOptions, Metadata};
+use std::io;
+
+mod common;
+use common::keypair;
+
+fn metadata() -> Metadata {
+    Metadata {
+        filename: Some("blob-7.tar".to_string()),
+        size: Some(14),
+        mtime: Some(1_700_000_000),
+        content_type: Some("application/x-tar".to_string()),
+        notes: Some("tax records, 2023".to_string()),
+    }
+}
+
+fn encrypt(recipients: &[AgeRecipient], msg: &[u8]) -> io::Result<Vec<u8>> {
+    let opts = EncryptOptions {
+        metadata: Some(metadata()),
+        ..Default::default()
+    };
+    common::encrypt_with(recipients, 2, &opts, msg)
+}
+
+#[test]
+fn metadata_roundtrip() -> io::Result<()> {
+    let msg = b"what is blob-7";
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..3).map(|_| keypair()).unzip();
+    let enc = encrypt(&recipients, msg)?;
+    // Nothing about the file shows in the header.
+    assert!(!String::from_utf8_lossy(&enc).contains("blob-7"));
+
+    let mut buf = vec![];
+    age_threshold::decrypt(&ids[1..], &mut &enc[..], &mut buf)?;
+    assert_eq!(&buf[..], &msg[..]);
+
+    let mut seen = None;
+    let mut buf = vec![];
+    age_threshold::decrypt_with_metadata(&ids[..2], &mut &enc[..], |m| {
+        seen = m.cloned();
+        Ok(&mut buf)
+    })?;
+    assert_eq!(seen, Some(metadata()));
+    assert_eq!(&buf[..], &msg[..]);
+
+    let header = age_threshold::inspect(&mut &enc[..])?;
+    assert_eq!(
+        age_threshold::read_metadata(&ids[..2], &header)?,
+        Some(metadata())
+    );
+    let err = age_threshold::read_metadata(&ids[..1], &header).unwrap_err();
+    assert_eq!(err.to_string(), "not enough shares, missing 2, 3");
+    Ok(())
+}
+
+#[test]
+fn no_metadata() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let mut enc = vec![];
+    age_threshold::encrypt(&recipients, 2, &mut &b"x"[..], &mut enc)?;
+    let header = age_threshold::inspect(&mut &enc[..])?;
+    assert_eq!(age_threshold::read_metadata(&ids, &header)?, None);
+    Ok(())
+}
+
+#[test]
+fn reject_tampered_metadata() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let enc = encrypt(&recipients, b"x")?;
+
+    // Flip a character of the encrypted metadata, keeping it valid base64.
+    let pos = enc
+        .windows(16)
+        .position(|w| w == b"-> ext metadata\n")
+        .unwrap()
+        + 16;
+    let mut tampered = enc.clone();
+    tampered[pos] = if enc[pos] == b'A' { b'B' } else { b'A' };
+
+    let header = age_threshold::inspect(&mut &tampered[..])?;
+    let err = age_threshold::read_metadata(&ids, &header).unwrap_err();
+    assert_eq!(err.to_string(), "metadata decryption failed");
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use age_threshold::types::{EncryptOptions, Padding, SigningKey};
+use std::io;
+
+mod common;
+use common::{encrypt_with, keypair};
+
+/// The length of the payload, after the `---` line of the header.
+fn payload_len(enc: &[u8]) -> usize {
+    let mac = enc.windows(4).position(|w| w == b"\n---").unwrap() + 1;
+    let end = mac + enc[mac..].iter().position(|&b| b == b'\n').unwrap() + 1;
+    enc.len() - end
+}
+
+#[test]
+fn padded_roundtrip() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..3).map(|_| keypair()).unzip();
+    let opts = EncryptOptions {
+        padding: Some(Padding::default()),
+        ..Default::default()
+    };
+    // Empty, short, and over several chunks.
+    for len in [0, 5, 1000, 200_000] {
+        let msg: Vec<u8> = (0..len).map(|i| (i % 251) as u8 + 1).collect();
+        let enc = encrypt_with(&recipients, 2, &opts, &msg)?;
+        let mut buf = vec![];
+        age_threshold::decrypt(&ids[1..], &mut &enc[..], &mut buf)?;
+        assert_eq!(buf, msg);
+    }
+
+    // Inputs that look like padding, within and across chunks.
+    for msg in [
+        vec![0x80],
+        vec![1, 0x80, 0, 0],
+        vec![0; 70_000],
+        [&[0x80][..], &[0; 70_000], &[0x80, 0]].concat(),
+    ] {
+        let enc = encrypt_with(&recipients, 2, &opts, &msg)?;
+        let mut buf = vec![];
+        age_threshold::decrypt(&ids[1..], &mut &enc[..], &mut buf)?;
+        assert_eq!(buf, msg);
+    }
+
+    // Lengths that round to the same size cannot be told apart.
+    let a = encrypt_with(&recipients, 2, &opts, &[1; 1000])?;
+    let b = encrypt_with(&recipients, 2, &opts, &[1; 1010])?;
+    assert_eq!(payload_len(&a), payload_len(&b));
+
+    // Signatures cover the padded payload.
+    let opts = EncryptOptions {
+        padding: Some(Padding::default()),
+        signer: Some(SigningKey::generate()),
+        ..Default::default()
+    };
+    let enc = encrypt_with(&recipients, 2, &opts, b"signed and padded")?;
+    let mut buf = vec![];
+    age_threshold::decrypt(&ids[..2], &mut &enc[..], &mut buf)?;
+    assert_eq!(&buf[..], b"signed and padded");
+    Ok(())
+}
+
+#[test]
+fn minimum_size() -> io::Result<()> {
+    let (_, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let opts = EncryptOptions {
+        padding: Some(Padding { min_size: 4096 }),
+        ..Default::default()
+    };
+    let password = encrypt_with(&recipients, 2, &opts, b"hunter2")?;
+    let key = encrypt_with(&recipients, 2, &opts, &[7; 2000])?;
+    assert_eq!(payload_len(&password), payload_len(&key));
+
+    let unpadded = encrypt_with(&recipients, 2, &Default::default(), b"hunter2")?;
+    assert!(payload_len(&unpadded) < 100);
+    Ok(())
+}
+
+#[test]
+fn padding_is_authenticated() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let opts = EncryptOptions {
+        padding: Some(Padding::default()),
+        ..Default::default()
+    };
+    let enc = encrypt_with(&recipients, 2, &opts, b"x")?;
+    let stanza = b"-> crit padding\n\n";
+    let pos = enc.windows(stanza.len()).position(|w| w == stanza).unwrap();
+
+    // Removing the extension does not expose the padding.
+    let stripped = [&enc[..pos], &enc[pos + stanza.len()..]].concat();
+    assert!(age_threshold::decrypt(&ids, &mut &stripped[..], &mut io::sink()).is_err());
+
+    // Nor does adding it to an unpadded file.
+    let unpadded = encrypt_with(&recipients, 2, &Default::default(), b"x")?;
+    let share = unpadded
+        .windows(12)
+        .position(|w| w == b"-> share-v1 ")
+        .unwrap();
+    let added = [&unpadded[..share], stanza, &unpadded[share..]].concat();
+    assert!(age_threshold::decrypt(&ids, &mut &added[..], &mut io::sink()).is_err());
+
+    let err =
+        age_threshold::to_age(&ids, &recipients[..1], &mut &enc[..], &mut io::sink()).unwrap_err();
+    assert_eq!(err.to_string(), "padded files cannot be converted to age");
+    Ok(())
+}
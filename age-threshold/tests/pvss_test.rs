@@ -0,0 +1,52 @@
+use std::io;
+
+use age_threshold::types::RistrettoIdentity;
+use age_threshold::{decrypt_pvss, encrypt_pvss, verify_pvss};
+
+#[test]
+fn encrypt_verify_decrypt() -> io::Result<()> {
+    let identities: Vec<_> = (0..3).map(|_| RistrettoIdentity::generate()).collect();
+    let recipients: Vec<_> = identities.iter().map(|i| i.to_public()).collect();
+    let mut enc = vec![];
+    encrypt_pvss(&recipients, 2, &mut &b"auditable"[..], &mut enc)?;
+    assert!(enc.starts_with(b"bbjubjub.fr/age-threshold/pvss/v0\n"));
+
+    assert_eq!(verify_pvss(&mut &enc[..])?, (2, 3));
+
+    let mut buf = vec![];
+    decrypt_pvss(&identities[1..], &mut &enc[..], &mut buf)?;
+    assert_eq!(&buf[..], b"auditable");
+    assert!(decrypt_pvss(&identities[..1], &mut &enc[..], &mut vec![]).is_err());
+    Ok(())
+}
+
+#[test]
+fn reject_swapped_shares() -> io::Result<()> {
+    let identities: Vec<_> = (0..2).map(|_| RistrettoIdentity::generate()).collect();
+    let recipients: Vec<_> = identities.iter().map(|i| i.to_public()).collect();
+    let mut enc = vec![];
+    encrypt_pvss(&recipients, 2, &mut &b"auditable"[..], &mut enc)?;
+
+    // Swap the encrypted values of the two shares, keeping everything else.
+    let end = enc.windows(4).position(|w| w == b"---\n").unwrap();
+    let header = String::from_utf8(enc[..end].to_vec()).unwrap();
+    let mut lines: Vec<Vec<&str>> = header.lines().map(|l| l.split(' ').collect()).collect();
+    let shares: Vec<_> = (0..lines.len())
+        .filter(|&i| lines[i].starts_with(&["->", "pvss-share"]))
+        .collect();
+    let encrypted = lines[shares[0]][4];
+    lines[shares[0]][4] = lines[shares[1]][4];
+    lines[shares[1]][4] = encrypted;
+    let mut tampered = String::new();
+    for l in lines {
+        tampered.push_str(&l.join(" "));
+        tampered.push('\n');
+    }
+    let mut tampered = tampered.into_bytes();
+    tampered.extend_from_slice(&enc[end..]);
+
+    let err = verify_pvss(&mut &tampered[..]).unwrap_err();
+    assert_eq!(err.to_string(), "inconsistent share 1");
+    assert!(decrypt_pvss(&identities, &mut &tampered[..], &mut vec![]).is_err());
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use age::secrecy::ExposeSecret;
+use age_threshold::types::{AgeIdentity, AgeRecipient};
+use std::io;
+
+mod common;
+use common::keypair;
+
+/// Map share indices 1, 2, ... to `recipients` in order.
+fn by_index(recipients: &[AgeRecipient]) -> Vec<(u32, AgeRecipient)> {
+    (1..).zip(recipients.iter().cloned()).collect()
+}
+
+fn copy(identity: &AgeIdentity) -> AgeIdentity {
+    AgeIdentity::from_bech32(identity.to_bech32().expose_secret()).unwrap()
+}
+
+#[test]
+fn refresh_keeps_secret() -> io::Result<()> {
+    let msg = b"same key, new shares";
+    let (mut ids, recipients): (Vec<_>, Vec<_>) = (0..3).map(|_| keypair()).unzip();
+
+    let mut enc = vec![];
+    age_threshold::encrypt(&recipients, 2, &mut &msg[..], &mut enc)?;
+    let mut refreshed = vec![];
+    age_threshold::refresh(&by_index(&recipients), &mut &enc[..], &mut refreshed)?;
+    let mut twice = vec![];
+    age_threshold::refresh(&by_index(&recipients), &mut &refreshed[..], &mut twice)?;
+
+    let old = age_threshold::decrypt_shares(&ids[..1], &mut &enc[..])?;
+    let new = age_threshold::decrypt_shares(&ids[..1], &mut &twice[..])?;
+    assert_eq!(old.len(), 1);
+    assert_eq!(new.len(), 1);
+    assert_eq!(old[0].index, new[0].index);
+    assert_ne!(old[0], new[0]);
+
+    let mut buf = vec![];
+    age_threshold::decrypt(&ids[..2], &mut &twice[..], &mut buf)?;
+    assert_eq!(&buf[..], &msg[..]);
+
+    // Rewrapping replaces all the groups of an index by one.
+    let (new_id, new_r) = keypair();
+    let mut rewrapped = vec![];
+    age_threshold::rewrap(&ids[..1], &[new_r], &mut &twice[..], &mut rewrapped)?;
+    let header = age_threshold::inspect(&mut &rewrapped[..])?;
+    let groups = |index| {
+        header
+            .enc_shares
+            .iter()
+            .filter(|es| es.index == index)
+            .count()
+    };
+    assert_eq!((groups(1), groups(2), groups(3)), (1, 3, 3));
+    ids[0] = new_id;
+    let mut buf = vec![];
+    age_threshold::decrypt(&ids[..2], &mut &rewrapped[..], &mut buf)?;
+    assert_eq!(&buf[..], &msg[..]);
+    Ok(())
+}
+
+#[test]
+fn holders_refresh_to_new_keys() -> io::Result<()> {
+    let msg = b"rotated by the holders themselves";
+    let (old_ids, old_recipients): (Vec<_>, Vec<_>) = (0..3).map(|_| keypair()).unzip();
+    let (new_ids, new_recipients): (Vec<_>, Vec<_>) = (0..3).map(|_| keypair()).unzip();
+    let mut file = vec![];
+    age_threshold::encrypt(&old_recipients, 2, &mut &msg[..], &mut file)?;
+    let leaked = age_threshold::decrypt_shares(&old_ids[..1], &mut &file[..])?;
+
+    // Each holder deals a sharing of zero to the new keys in turn,
+    // so that no single one of them knows the new shares.
+    for _ in 0..3 {
+        let mut dealt = vec![];
+        age_threshold::refresh(&by_index(&new_recipients), &mut &file[..], &mut dealt)?;
+        file = dealt;
+    }
+    // Then each moves its share to its new key, with both keys at hand.
+    for ((old_id, new_id), new_r) in old_ids.iter().zip(&new_ids).zip(&new_recipients) {
+        let mut rewrapped = vec![];
+        age_threshold::rewrap(
+            &[copy(old_id), copy(new_id)],
+            std::slice::from_ref(new_r),
+            &mut &file[..],
+            &mut rewrapped,
+        )?;
+        file = rewrapped;
+    }
+
+    // The header is back to one group per holder, all under the new keys.
+    let header = age_threshold::inspect(&mut &file[..])?;
+    let indices: Vec<_> = header.enc_shares.iter().map(|es| es.index).collect();
+    assert_eq!(indices, [1, 2, 3]);
+    assert!(age_threshold::decrypt_shares(&old_ids, &mut &file[..])?.is_empty());
+    let refreshed = age_threshold::decrypt_shares(&new_ids[..1], &mut &file[..])?;
+    assert_eq!(refreshed[0].index, leaked[0].index);
+    assert_ne!(refreshed[0], leaked[0]);
+
+    let mut buf = vec![];
+    age_threshold::decrypt(&new_ids[1..], &mut &file[..], &mut buf)?;
+    assert_eq!(&buf[..], &msg[..]);
+    Ok(())
+}
+
+#[test]
+fn refresh_holder_mapping() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..3).map(|_| keypair()).unzip();
+    let (_, extra_r) = keypair();
+    let enc = common::encrypt(&recipients, 2, b"x")?;
+
+    // Holders in any order, with two recipients for index 2.
+    let holders = [
+        (3, recipients[2].clone()),
+        (2, recipients[1].clone()),
+        (2, extra_r),
+        (1, recipients[0].clone()),
+    ];
+    let mut refreshed = vec![];
+    age_threshold::refresh(&holders, &mut &enc[..], &mut refreshed)?;
+    let header = age_threshold::inspect(&mut &refreshed[..])?;
+    let stanzas = |index| {
+        let es = header.enc_shares.iter().rfind(|es| es.index == index);
+        es.unwrap().stanzas.len()
+    };
+    assert_eq!((stanzas(1), stanzas(2), stanzas(3)), (1, 2, 1));
+    let mut buf = vec![];
+    age_threshold::decrypt(&ids[..2], &mut &refreshed[..], &mut buf)?;
+    assert_eq!(&buf[..], b"x");
+
+    let err = age_threshold::refresh(&holders[..3], &mut &enc[..], &mut io::sink()).unwrap_err();
+    assert_eq!(err.to_string(), "no recipient for share index 1");
+    let mut holders = by_index(&recipients);
+    holders.push((4, recipients[0].clone()));
+    let err = age_threshold::refresh(&holders, &mut &enc[..], &mut io::sink()).unwrap_err();
+    assert_eq!(err.to_string(), "no share index 4");
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use std::io;
+
+use age_threshold::types::SigningKey;
+use age_threshold::{encrypt, fulfill, read_request, request, respond};
+
+mod common;
+use common::keypair;
+
+#[test]
+fn request_respond_fulfill() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..3).map(|_| keypair()).unzip();
+    let mut enc = vec![];
+    encrypt(&recipients, 2, &mut &b"quorum"[..], &mut enc)?;
+
+    let mut req = vec![];
+    let coordinator = request(None, &mut &enc[..], &mut req)?;
+
+    let mut responses = vec![];
+    let mut fingerprints = vec![];
+    for identity in &ids[1..] {
+        let mut response = vec![];
+        let identities = std::slice::from_ref(identity);
+        fingerprints.push(respond(identities, "", &mut &req[..], &mut response)?);
+        assert!(response.starts_with(b"-----BEGIN AGE ENCRYPTED FILE-----"));
+        responses.push(response);
+    }
+    assert_eq!(fingerprints[0], fingerprints[1]);
+
+    let mut buf = vec![];
+    fulfill(&coordinator, &responses, &mut &enc[..], &mut buf)?;
+    assert_eq!(&buf[..], b"quorum");
+
+    // The same response twice does not count as two shares.
+    let twice = [responses[0].clone(), responses[0].clone()];
+    assert!(fulfill(&coordinator, &twice, &mut &enc[..], &mut vec![]).is_err());
+
+    // Responses are only readable by the coordinator.
+    let other = age::x25519::Identity::generate();
+    assert!(fulfill(&other, &responses, &mut &enc[..], &mut vec![]).is_err());
+    Ok(())
+}
+
+#[test]
+fn reject_response_for_other_file() -> io::Result<()> {
+    let (identity, recipient) = keypair();
+    let identities = [identity];
+    let mut a = vec![];
+    encrypt(std::slice::from_ref(&recipient), 1, &mut &b"a"[..], &mut a)?;
+    let mut b = vec![];
+    encrypt(&[recipient], 1, &mut &b"b"[..], &mut b)?;
+
+    let mut req = vec![];
+    let coordinator = request(None, &mut &a[..], &mut req)?;
+    let mut response = vec![];
+    respond(&identities, "", &mut &req[..], &mut response)?;
+
+    assert!(fulfill(&coordinator, &[response], &mut &b[..], &mut vec![]).is_err());
+    assert!(respond(&identities, "", &mut &a[..], &mut vec![]).is_err());
+    Ok(())
+}
+
+#[test]
+fn decrypt_asking_holders() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..3).map(|_| keypair()).unzip();
+    let mut enc = vec![];
+    encrypt(&recipients, 3, &mut &b"fan out"[..], &mut enc)?;
+
+    let local = &ids[..1];
+    let mut asked = 0;
+    let ask = |req: &[u8]| {
+        asked += 1;
+        let identities = std::slice::from_ref(ids.get(asked)?);
+        let mut response = vec![];
+        Some(respond(identities, "", &mut &req[..], &mut response).map(|_| response))
+    };
+    let mut buf = vec![];
+    age_threshold::decrypt_with_requests(local, None, ask, &mut &enc[..], &mut buf)?;
+    assert_eq!(&buf[..], b"fan out");
+
+    let refuse = |_: &[u8]| Some(Err(io::Error::other("refused")));
+    let mut calls = 0;
+    let refuse_twice = |req: &[u8]| {
+        calls += 1;
+        (calls <= 2).then(|| refuse(req)).flatten()
+    };
+    let err =
+        age_threshold::decrypt_with_requests(local, None, refuse_twice, &mut &enc[..], &mut vec![])
+            .unwrap_err();
+    assert_eq!(err.to_string(), "not enough shares: refused, refused");
+    Ok(())
+}
+
+#[test]
+fn signed_request() -> io::Result<()> {
+    let (_, recipient) = keypair();
+    let mut enc = vec![];
+    encrypt(&[recipient], 1, &mut &b"x"[..], &mut enc)?;
+    let coordinator = SigningKey::generate();
+
+    let mut req = vec![];
+    request(Some(&coordinator), &mut &enc[..], &mut req)?;
+    let signer = read_request(&mut &req[..])?.signer;
+    assert_eq!(signer, Some(coordinator.verifying_key()));
+
+    // The signature does not carry over to a request for another recipient.
+    let mut other = vec![];
+    request(None, &mut &enc[..], &mut other)?;
+    let signature = req.split(|&c| c == b'\n').nth(2).unwrap();
+    let mut lines: Vec<_> = other.split(|&c| c == b'\n').collect();
+    lines.insert(2, signature);
+    let forged = lines.join(&b'\n');
+    let Err(err) = read_request(&mut &forged[..]) else {
+        panic!("forged request accepted");
+    };
+    assert_eq!(err.to_string(), "invalid request signature");
+    Ok(())
+}
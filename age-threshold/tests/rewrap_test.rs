@@ -0,0 +1,50 @@
+use std::io;
+
+mod common;
+use common::keypair;
+
+#[test]
+fn rewrap_one_holder() -> io::Result<()> {
+    let msg = b"rotate my key please";
+    let (id1, r1) = keypair();
+    let (id2, r2) = keypair();
+    let (id3, r3) = keypair();
+    let (new_id, new_r) = keypair();
+
+    let mut enc = vec![];
+    age_threshold::encrypt(&[r1, r2, r3], 2, &mut &msg[..], &mut enc)?;
+
+    let mut rewrapped = vec![];
+    age_threshold::rewrap(
+        std::slice::from_ref(&id1),
+        &[new_r],
+        &mut &enc[..],
+        &mut rewrapped,
+    )?;
+
+    // The payload is carried over verbatim.
+    let payload_len = enc.len() - enc.windows(4).position(|w| w == b"---\n").unwrap();
+    assert_eq!(
+        enc[enc.len() - payload_len..],
+        rewrapped[rewrapped.len() - payload_len..]
+    );
+
+    let mut buf = vec![];
+    age_threshold::decrypt(&[new_id, id2], &mut &rewrapped[..], &mut buf)?;
+    assert_eq!(&buf[..], &msg[..]);
+
+    // The old key no longer unlocks its share.
+    assert!(age_threshold::decrypt(&[id1, id3], &mut &rewrapped[..], &mut io::sink()).is_err());
+    Ok(())
+}
+
+#[test]
+fn rewrap_unknown_identity() -> io::Result<()> {
+    let (_, r1) = keypair();
+    let (other, new_r) = keypair();
+
+    let mut enc = vec![];
+    age_threshold::encrypt(&[r1], 1, &mut &b"x"[..], &mut enc)?;
+    assert!(age_threshold::rewrap(&[other], &[new_r], &mut &enc[..], &mut io::sink()).is_err());
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::io;
+
+use age::secrecy::SecretString;
+use age_threshold::types::{Header, ShareFile};
+use age_threshold::{collect_shares, decrypt_with_shares, encrypt, read_session, write_session};
+
+mod common;
+use common::keypair;
+
+fn approve(_: &Header, _: &[ShareFile]) -> io::Result<()> {
+    Ok(())
+}
+
+fn passphrase() -> SecretString {
+    SecretString::new("correct horse battery staple".to_string())
+}
+
+#[test]
+fn collect_one_holder_at_a_time() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..3).map(|_| keypair()).unzip();
+    let mut enc = vec![];
+    encrypt(&recipients, 2, &mut &b"ceremony"[..], &mut enc)?;
+
+    let mut session = vec![];
+    let first = &ids[..1];
+    assert_eq!(
+        collect_shares(first, approve, &mut session, &mut &enc[..])?,
+        (1, 2)
+    );
+    // The same holder twice does not count as two shares.
+    assert_eq!(
+        collect_shares(first, approve, &mut session, &mut &enc[..])?,
+        (1, 2)
+    );
+    assert!(decrypt_with_shares(&session, &mut &enc[..], &mut vec![]).is_err());
+
+    let mut sealed = vec![];
+    write_session(&session, passphrase(), &mut sealed)?;
+    assert!(sealed.starts_with(b"-----BEGIN AGE ENCRYPTED FILE-----"));
+    let wrong = SecretString::new("wrong".to_string());
+    assert!(read_session(wrong, &mut &sealed[..]).is_err());
+    let mut session = read_session(passphrase(), &mut &sealed[..])?;
+
+    let last = &ids[2..];
+    assert_eq!(
+        collect_shares(last, approve, &mut session, &mut &enc[..])?,
+        (2, 2)
+    );
+    let mut buf = vec![];
+    decrypt_with_shares(&session, &mut &enc[..], &mut buf)?;
+    assert_eq!(&buf[..], b"ceremony");
+
+    // A session is bound to the file it was started for.
+    let mut other = vec![];
+    encrypt(&recipients, 2, &mut &b"other"[..], &mut other)?;
+    assert!(collect_shares(last, approve, &mut session, &mut &other[..]).is_err());
+    assert!(decrypt_with_shares(&session, &mut &other[..], &mut vec![]).is_err());
+    Ok(())
+}
+
+#[test]
+fn declined_shares_stay_out() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let mut enc = vec![];
+    encrypt(&recipients, 2, &mut &b"no"[..], &mut enc)?;
+
+    let mut session = vec![];
+    let mut shown = vec![];
+    let decline = |header: &Header, shares: &[ShareFile]| {
+        shown.push((header.threshold, shares[0].share.index));
+        Err(io::Error::other("release declined"))
+    };
+    let err = collect_shares(&ids[1..], decline, &mut session, &mut &enc[..]).unwrap_err();
+    assert_eq!(err.to_string(), "release declined");
+    assert_eq!(shown, [(2, 2)]);
+    assert!(session.is_empty());
+    Ok(())
+}
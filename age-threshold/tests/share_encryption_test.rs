@@ -0,0 +1,84 @@
+use std::io;
+
+mod common;
+use common::keypair;
+
+/// Split a file into its header text, up to the HMAC line, and the rest.
+fn split(enc: &[u8]) -> (String, &[u8]) {
+    let end = enc.windows(4).position(|w| w == b"---\n").unwrap();
+    (String::from_utf8(enc[..end].to_vec()).unwrap(), &enc[end..])
+}
+
+/// The lines of the share group with index `i`, from its share stanza to the next one.
+fn share_group(header: &str, i: u32) -> std::ops::Range<usize> {
+    let start = header.find(&format!("-> share-v1 {} ", i)).unwrap();
+    let end = header[start + 1..]
+        .find("-> share-v1 ")
+        .map_or(header.len(), |e| start + 1 + e);
+    start..end
+}
+
+#[test]
+fn reject_tampered_share() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let mut enc = vec![];
+    age_threshold::encrypt(&recipients, 2, &mut &b"x"[..], &mut enc)?;
+    let (mut header, rest) = split(&enc);
+    assert!(!header.contains("\n-> share "));
+
+    // Flip a character of the encrypted `s` of share 1, keeping it valid base64.
+    let range = share_group(&header, 1);
+    let pos = range.start + "-> share-v1 1 ".len();
+    let c = if header.as_bytes()[pos] == b'A' {
+        "B"
+    } else {
+        "A"
+    };
+    header.replace_range(pos..pos + 1, c);
+    let tampered = [header.as_bytes(), rest].concat();
+
+    let err = age_threshold::decrypt(&ids, &mut &tampered[..], &mut io::sink()).unwrap_err();
+    assert_eq!(err.to_string(), "share decryption failed");
+    Ok(())
+}
+
+#[test]
+fn reject_transplanted_share() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let (mut first, mut second) = (vec![], vec![]);
+    age_threshold::encrypt(&recipients, 2, &mut &b"first"[..], &mut first)?;
+    age_threshold::encrypt(&recipients, 2, &mut &b"second"[..], &mut second)?;
+
+    // Move the first file's share 1 into the second file.
+    let (first_header, _) = split(&first);
+    let (mut header, rest) = split(&second);
+    let group = first_header[share_group(&first_header, 1)].to_string();
+    header.replace_range(share_group(&header, 1), &group);
+    let transplanted = [header.as_bytes(), rest].concat();
+
+    let err = age_threshold::decrypt(&ids, &mut &transplanted[..], &mut io::sink()).unwrap_err();
+    assert_eq!(err.to_string(), "share decryption failed");
+    Ok(())
+}
+
+#[test]
+fn reject_legacy_share_in_v1() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let mut enc = vec![];
+    age_threshold::encrypt(&recipients, 2, &mut &b"x"[..], &mut enc)?;
+    let (mut header, rest) = split(&enc);
+
+    // Rewrite share 1 as an unauthenticated legacy share stanza.
+    let range = share_group(&header, 1);
+    let line_end = range.start + header[range.start..].find('\n').unwrap();
+    let mut args: Vec<_> = header[range.start..line_end].split(' ').collect();
+    args[1] = "share";
+    args.pop();
+    let legacy = args.join(" ");
+    header.replace_range(range.start..line_end, &legacy);
+    let downgraded = [header.as_bytes(), rest].concat();
+
+    let err = age_threshold::decrypt(&ids, &mut &downgraded[..], &mut io::sink()).unwrap_err();
+    assert_eq!(err.to_string(), "parse error");
+    Ok(())
+}
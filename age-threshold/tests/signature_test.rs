@@ -0,0 +1,148 @@
+use age_threshold::types::{AgeRecipient, EncryptOptions, SigningKey};
+use std::io;
+
+mod common;
+use common::keypair;
+
+fn encrypt(recipients: &[AgeRecipient], signer: &SigningKey, msg: &[u8]) -> io::Result<Vec<u8>> {
+    let opts = EncryptOptions {
+        signer: Some(signer.clone()),
+        ..Default::default()
+    };
+    common::encrypt_with(recipients, 2, &opts, msg)
+}
+
+#[test]
+fn signed_roundtrip() -> io::Result<()> {
+    let msg = b"from the treasurer";
+    let signer = SigningKey::generate();
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..3).map(|_| keypair()).unzip();
+    let enc = encrypt(&recipients, &signer, msg)?;
+
+    let header = age_threshold::inspect(&mut &enc[..])?;
+    assert_eq!(
+        age_threshold::signer(&header)?,
+        Some(signer.verifying_key())
+    );
+    assert_eq!(
+        age_threshold::export_header_shares(&ids[..1], &header)?.len(),
+        1
+    );
+
+    let mut buf = vec![];
+    age_threshold::decrypt(&ids[1..], &mut &enc[..], &mut buf)?;
+    assert_eq!(&buf[..], &msg[..]);
+
+    let mut buf = vec![];
+    let signers = [
+        SigningKey::generate().verifying_key(),
+        signer.verifying_key(),
+    ];
+    age_threshold::decrypt_signed(&ids, &signers, &mut &enc[..], &mut buf)?;
+    assert_eq!(&buf[..], &msg[..]);
+
+    // Refreshing keeps the constant term of the sharing, so the signature still holds.
+    let holders: Vec<_> = (1..).zip(recipients).collect();
+    let mut refreshed = vec![];
+    age_threshold::refresh(&holders, &mut &enc[..], &mut refreshed)?;
+    let header = age_threshold::inspect(&mut &refreshed[..])?;
+    assert_eq!(
+        age_threshold::signer(&header)?,
+        Some(signer.verifying_key())
+    );
+    Ok(())
+}
+
+#[test]
+fn require_signer() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let signer = SigningKey::generate();
+    let other = SigningKey::generate().verifying_key();
+
+    let enc = encrypt(&recipients, &signer, b"x")?;
+    let err =
+        age_threshold::decrypt_signed(&ids, &[other], &mut &enc[..], &mut io::sink()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("unexpected signer {}", signer.verifying_key())
+    );
+
+    let mut unsigned = vec![];
+    age_threshold::encrypt(&recipients, 2, &mut &b"x"[..], &mut unsigned)?;
+    let header = age_threshold::inspect(&mut &unsigned[..])?;
+    assert_eq!(age_threshold::signer(&header)?, None);
+    let err = age_threshold::decrypt_signed(&ids, &[other], &mut &unsigned[..], &mut io::sink())
+        .unwrap_err();
+    assert_eq!(err.to_string(), "file is not signed");
+    Ok(())
+}
+
+#[test]
+fn reject_tampered_signature() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let signer = SigningKey::generate();
+    let enc = encrypt(&recipients, &signer, b"x")?;
+
+    // Claim another payload digest.
+    let text = String::from_utf8_lossy(&enc).to_string();
+    let start = text.find("-> ext signature ").unwrap();
+    let end = start + text[start..].find('\n').unwrap();
+    let mut words: Vec<_> = text[start..end].split(' ').collect();
+    let other = "A".repeat(43) + "=";
+    *words.last_mut().unwrap() = &other;
+    let tampered = [&enc[..start], words.join(" ").as_bytes(), &enc[end..]].concat();
+
+    let header = age_threshold::inspect(&mut &tampered[..])?;
+    let err = age_threshold::signer(&header).unwrap_err();
+    assert_eq!(err.to_string(), "invalid signature");
+    let err = age_threshold::export_header_shares(&ids[..1], &header).unwrap_err();
+    assert_eq!(err.to_string(), "invalid signature");
+    let err = age_threshold::decrypt(&ids, &mut &tampered[..], &mut io::sink()).unwrap_err();
+    assert_eq!(err.to_string(), "invalid signature");
+    Ok(())
+}
+
+#[test]
+fn signed_payload_over_several_chunks() -> io::Result<()> {
+    let msg: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+    let signer = SigningKey::generate();
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let enc = encrypt(&recipients, &signer, &msg)?;
+
+    let mut buf = vec![];
+    age_threshold::decrypt_signed(&ids, &[signer.verifying_key()], &mut &enc[..], &mut buf)?;
+    assert_eq!(buf, msg);
+
+    // The digest is checked before any plaintext is written, even of the intact first chunk.
+    let mut tampered = enc.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    let mut buf = vec![];
+    let err = age_threshold::decrypt(&ids, &mut &tampered[..], &mut buf).unwrap_err();
+    assert_eq!(err.to_string(), "payload does not match its signature");
+    assert!(buf.is_empty());
+    Ok(())
+}
+
+#[test]
+fn reject_relabeled() -> io::Result<()> {
+    let (_, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let opts = EncryptOptions {
+        signer: Some(SigningKey::generate()),
+        labels: vec![Some("alice".to_string()), Some("bob".to_string())],
+        ..Default::default()
+    };
+    let enc = common::encrypt_with(&recipients, 2, &opts, b"x")?;
+
+    // Swap the names of the holders.
+    let text = String::from_utf8_lossy(&enc).to_string();
+    let relabeled = text
+        .replace("-> ext label 1\n", "-> ext label 3\n")
+        .replace("-> ext label 2\n", "-> ext label 1\n")
+        .replace("-> ext label 3\n", "-> ext label 2\n");
+    assert_ne!(relabeled, text);
+    let header = age_threshold::inspect(&mut relabeled.as_bytes())?;
+    assert_eq!(header.label(1), Some("bob"));
+    let err = age_threshold::signer(&header).unwrap_err();
+    assert_eq!(err.to_string(), "invalid signature");
+    Ok(())
+}
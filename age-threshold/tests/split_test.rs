@@ -0,0 +1,51 @@
+use std::io;
+
+use age_threshold::types::SplitShare;
+use age_threshold::{combine, split};
+
+#[test]
+fn split_and_combine() -> io::Result<()> {
+    let secret = b"correct horse battery staple, and then some more words to span chunks";
+    let shares = split(secret, 3, 5)?;
+    let printed: Vec<_> = shares.iter().map(|s| s.to_bech32()).collect();
+    let parsed: Vec<_> = [&printed[3], &printed[1], &printed[4]]
+        .iter()
+        .map(|s| SplitShare::from_bech32(s).unwrap())
+        .collect();
+    assert_eq!(combine(&parsed)?, secret);
+
+    assert!(combine(&parsed[..2]).is_err());
+    assert!(combine(&[parsed[0].clone(), parsed[0].clone(), parsed[1].clone()]).is_err());
+    assert!(split(b"", 2, 3).is_err());
+    assert!(split(secret, 4, 3).is_err());
+    Ok(())
+}
+
+#[test]
+fn split_and_combine_long_secrets() -> io::Result<()> {
+    // Shares of these run past the 1023 characters of plain Bech32.
+    for len in [100, 300] {
+        let secret: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        let shares = split(&secret, 3, 5)?;
+        let printed: Vec<_> = shares.iter().map(|s| s.to_bech32()).collect();
+        assert!(printed[0].len() > 1023);
+        let parsed: Vec<_> = printed[2..]
+            .iter()
+            .map(|s| SplitShare::from_bech32(s).unwrap())
+            .collect();
+        assert_eq!(combine(&parsed)?, secret);
+    }
+    Ok(())
+}
+
+#[test]
+fn reject_mixed_shares() -> io::Result<()> {
+    let a = split(b"first secret", 2, 3)?;
+    let b = split(b"other secret", 2, 3)?;
+    assert!(combine(&[a[0].clone(), b[1].clone()]).is_err());
+
+    let mut tampered = a[1].clone();
+    tampered.shares[0].s += curve25519_dalek::scalar::Scalar::ONE;
+    assert!(combine(&[a[0].clone(), tampered]).is_err());
+    Ok(())
+}
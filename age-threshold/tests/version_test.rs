@@ -0,0 +1,184 @@
+use age_core::primitives::hkdf;
+use age_core::secrecy::ExposeSecret;
+use age_threshold::types::{CommitmentScheme, Commitments, EncryptOptions, HeaderVersion, Padding};
+use chacha20poly1305::{AeadInPlace, ChaCha20Poly1305, KeyInit};
+use std::io;
+
+mod common;
+use common::keypair;
+
+/// Insert a stanza just before the first share stanza of an encrypted file.
+fn inject(enc: &[u8], stanza: &str) -> Vec<u8> {
+    let pos = enc
+        .windows(9)
+        .position(|w| w == b"-> share ")
+        .unwrap_or_else(|| enc.windows(12).position(|w| w == b"-> share-v1 ").unwrap());
+    [&enc[..pos], stanza.as_bytes(), &enc[pos..]].concat()
+}
+
+#[test]
+fn write_newest_version() -> io::Result<()> {
+    let (_, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let mut enc = vec![];
+    age_threshold::encrypt(&recipients, 2, &mut &b"x"[..], &mut enc)?;
+    assert!(enc.starts_with(b"bbjubjub.fr/age-threshold/v1\n"));
+    Ok(())
+}
+
+#[test]
+fn pin_version() -> io::Result<()> {
+    let msg = b"for older readers";
+    let (mut ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let opts = EncryptOptions {
+        version: HeaderVersion::V0,
+        ..Default::default()
+    };
+    let mut enc = vec![];
+    age_threshold::encrypt_with(&recipients, 2, &opts, &mut &msg[..], &mut enc)?;
+    assert!(enc.starts_with(b"bbjubjub.fr/age-threshold/v0\n"));
+    assert_legacy(&enc)?;
+
+    // Rewriting the header keeps its version.
+    let (new_id, new_r) = keypair();
+    let mut rewrapped = vec![];
+    age_threshold::rewrap(&ids[..1], &[new_r], &mut &enc[..], &mut rewrapped)?;
+    assert!(rewrapped.starts_with(b"bbjubjub.fr/age-threshold/v0\n"));
+    assert_legacy(&rewrapped)?;
+
+    ids[0] = new_id;
+    let mut buf = vec![];
+    age_threshold::decrypt(&ids, &mut &rewrapped[..], &mut buf)?;
+    assert_eq!(&buf[..], &msg[..]);
+    Ok(())
+}
+
+#[test]
+fn pin_version_old_reader() -> io::Result<()> {
+    let msg: Vec<u8> = (0..150_000u32).map(|i| (i % 251) as u8).collect();
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let opts = EncryptOptions {
+        version: HeaderVersion::V0,
+        ..Default::default()
+    };
+    let mut enc = vec![];
+    age_threshold::encrypt_with(&recipients, 2, &opts, &mut &msg[..], &mut enc)?;
+
+    // Decrypt the payload the way three 0.1.0 does, with a little-endian chunk counter.
+    let mac = enc.windows(4).position(|w| w == b"\n---").unwrap() + 1;
+    let end = mac + enc[mac..].iter().position(|&c| c == b'\n').unwrap() + 1;
+    let file_key = age_threshold::unwrap_file_key(&ids, &enc[..end])?.unwrap();
+    let (nonce, payload) = enc[end..].split_at(16);
+    let key = hkdf(nonce, b"payload", file_key.expose_secret());
+    let aead = ChaCha20Poly1305::new(&key.into());
+    let chunks: Vec<_> = payload.chunks(64 * 1024 + 16).collect();
+    assert_eq!(chunks.len(), 3);
+    let mut buf = vec![];
+    for (counter, chunk) in chunks.iter().enumerate() {
+        let mut iv = [0; 12];
+        iv[..11].copy_from_slice(&(counter as u128).to_le_bytes()[..11]);
+        iv[11] = (counter == chunks.len() - 1) as u8;
+        let mut chunk = chunk.to_vec();
+        aead.decrypt_in_place((&iv).into(), b"", &mut chunk)
+            .map_err(io::Error::other)?;
+        buf.extend(chunk);
+    }
+    assert_eq!(buf, msg);
+    Ok(())
+}
+
+/// Check that a file only uses what readers of version 0 headers can parse:
+/// unauthenticated `share` stanzas under Pedersen commitments.
+fn assert_legacy(enc: &[u8]) -> io::Result<()> {
+    let header = age_threshold::inspect(&mut &enc[..])?;
+    assert!(header.extensions.is_empty());
+    assert!(matches!(header.commitments, Commitments::Pedersen(_)));
+    assert!(header.enc_shares.iter().all(|es| es.tag.is_none()));
+    let text = String::from_utf8_lossy(enc);
+    assert!(text.contains("\n-> share 1 "));
+    assert!(!text.contains("share-v1"));
+    Ok(())
+}
+
+#[test]
+fn pin_version_unsupported_features() -> io::Result<()> {
+    let (_, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    for (opts, err) in [
+        (
+            EncryptOptions {
+                scheme: CommitmentScheme::Hash,
+                ..Default::default()
+            },
+            "hash commitments need a newer header version",
+        ),
+        (
+            EncryptOptions {
+                padding: Some(Padding::default()),
+                ..Default::default()
+            },
+            "extensions need a newer header version",
+        ),
+    ] {
+        let opts = EncryptOptions {
+            version: HeaderVersion::V0,
+            ..opts
+        };
+        let mut enc = vec![];
+        let result = age_threshold::encrypt_with(&recipients, 2, &opts, &mut &b"x"[..], &mut enc);
+        assert_eq!(result.unwrap_err().to_string(), err);
+    }
+    Ok(())
+}
+
+#[test]
+fn skip_unknown_optional_extension() -> io::Result<()> {
+    let msg = b"from the future";
+    let (mut ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let mut enc = vec![];
+    age_threshold::encrypt(&recipients, 2, &mut &msg[..], &mut enc)?;
+    let enc = inject(&enc, "-> ext future-thing abc\nAAAA\n");
+
+    let mut buf = vec![];
+    age_threshold::decrypt(&ids, &mut &enc[..], &mut buf)?;
+    assert_eq!(&buf[..], &msg[..]);
+
+    // The extension is carried over when the header is rewritten.
+    let (new_id, new_r) = keypair();
+    let mut rewrapped = vec![];
+    age_threshold::rewrap(&ids[..1], &[new_r], &mut &enc[..], &mut rewrapped)?;
+    let text = String::from_utf8_lossy(&rewrapped);
+    assert!(text.contains("\n-> ext future-thing abc\nAAAA\n-> share-v1 1 "));
+    ids[0] = new_id;
+    age_threshold::decrypt(&ids, &mut &rewrapped[..], &mut io::sink())?;
+    Ok(())
+}
+
+#[test]
+fn refuse_unknown_critical_extension() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let mut enc = vec![];
+    age_threshold::encrypt(&recipients, 2, &mut &b"x"[..], &mut enc)?;
+    let enc = inject(&enc, "-> crit future-thing\n\n");
+
+    let err = age_threshold::decrypt(&ids, &mut &enc[..], &mut io::sink()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unsupported critical extension future-thing"
+    );
+    Ok(())
+}
+
+#[test]
+fn no_extensions_in_v0() -> io::Result<()> {
+    let (ids, recipients): (Vec<_>, Vec<_>) = (0..2).map(|_| keypair()).unzip();
+    let opts = EncryptOptions {
+        version: HeaderVersion::V0,
+        ..Default::default()
+    };
+    let mut enc = vec![];
+    age_threshold::encrypt_with(&recipients, 2, &opts, &mut &b"x"[..], &mut enc)?;
+    let enc = inject(&enc, "-> ext future-thing\n\n");
+
+    let err = age_threshold::decrypt(&ids, &mut &enc[..], &mut io::sink()).unwrap_err();
+    assert_eq!(err.to_string(), "parse error");
+    Ok(())
+}
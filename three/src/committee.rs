@@ -0,0 +1,124 @@
+use age::cli_common::file_io;
+use age::secrecy::ExposeSecret;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use age_threshold::types::{
+    CommitteeDealing, CommitteeHeader, CommitteeIdentity, CommitteeReveal, Deal, PartialDecryption,
+};
+
+use crate::{
+    create_file, read_identities, read_identity_file, read_recipients, read_text_file, release,
+    set_up_io, set_up_output, CommitteeCombineOpts, CommitteeDealOpts, CommitteeFinishOpts,
+    CommitteePartialOpts, CommitteeRevealOpts,
+};
+
+pub(crate) fn deal(opts: &CommitteeDealOpts) -> io::Result<()> {
+    let members = read_recipients(&opts.recipients, &opts.recipients_files)?;
+
+    let mut deal = vec![];
+    let dealing = age_threshold::committee_deal(opts.index, &members, opts.threshold, &mut deal)?;
+    let mut f = create_file(&opts.dealing, false, 0o600)?;
+    writeln!(
+        f,
+        "# dealing of member {}, to keep until the reveal",
+        dealing.dealer
+    )?;
+    writeln!(f, "{}", dealing.to_bech32().expose_secret())?;
+    let mut output = set_up_output(&opts.output, file_io::OutputFormat::Text)?;
+    output.write_all(&deal)
+}
+
+pub(crate) fn reveal(opts: &CommitteeRevealOpts) -> io::Result<()> {
+    let identities = read_identities(&opts.identities)?;
+    let dealing = first_line(&read_identity_file(&opts.dealing)?, &opts.dealing)?;
+    let dealing = CommitteeDealing::from_bech32(&dealing).map_err(io::Error::other)?;
+    let deals = read_deals(&opts.deals)?;
+
+    let reveal = age_threshold::committee_reveal(&identities, &dealing, &deals)?;
+    let mut output = set_up_output(&opts.output, file_io::OutputFormat::Text)?;
+    writeln!(output, "# reveal of member {}", reveal.dealer)?;
+    writeln!(output, "{}", reveal.to_bech32())
+}
+
+pub(crate) fn finish(opts: &CommitteeFinishOpts) -> io::Result<()> {
+    let identities = read_identities(&opts.identities)?;
+    let deals = read_deals(&opts.deals)?;
+    let mut reveals = vec![];
+    for path in &opts.reveals {
+        for l in read_text_file(path)?.iter().filter(|l| !l.is_empty()) {
+            reveals.push(CommitteeReveal::from_bech32(l).map_err(io::Error::other)?);
+        }
+    }
+
+    let identity = age_threshold::committee_finish(&identities, &deals, &reveals)?;
+    let recipient = identity.recipient.to_string();
+    let mut output = set_up_output(&opts.output, file_io::OutputFormat::Text)?;
+    writeln!(
+        output,
+        "# member {} of a committee, any {} of which decrypt",
+        identity.index,
+        identity.recipient.threshold()
+    )?;
+    writeln!(output, "# public key: {}", recipient)?;
+    writeln!(output, "{}", identity.to_bech32().expose_secret())?;
+    eprintln!("Committee recipient: {}", recipient);
+    eprintln!("Check that every member got the same one.");
+    Ok(())
+}
+
+pub(crate) fn partial(opts: &CommitteePartialOpts) -> io::Result<()> {
+    let identity = first_line(&read_identity_file(&opts.identity)?, &opts.identity)?;
+    let identity = CommitteeIdentity::from_bech32(&identity).map_err(io::Error::other)?;
+
+    let (mut input, mut output) =
+        set_up_io(&opts.input, &opts.output, file_io::OutputFormat::Text)?;
+    // Committee files are not signed, so there is no signer to require.
+    let policy = release::Policy::new(&opts.rules, &opts.audit_log, vec![])?;
+    let approve = |header: &CommitteeHeader| {
+        policy.approve(&release::Release {
+            action: "committee-partial",
+            fingerprint: header.fingerprint(),
+            threshold: header.threshold as u32,
+            indices: vec![identity.index],
+            label: String::new(),
+            requested: false,
+            requester: None,
+            signer: None,
+        })
+    };
+    let partial = age_threshold::partial_decrypt(&identity, approve, &mut input)?;
+    writeln!(output, "# partial decryption of member {}", partial.index)?;
+    writeln!(output, "{}", partial.to_bech32())
+}
+
+pub(crate) fn combine(opts: &CommitteeCombineOpts) -> io::Result<()> {
+    let mut partials = vec![];
+    for path in &opts.partials {
+        for l in read_text_file(path)?.iter().filter(|l| !l.is_empty()) {
+            partials.push(PartialDecryption::from_bech32(l).map_err(io::Error::other)?);
+        }
+    }
+
+    let (mut input, mut output) =
+        set_up_io(&opts.input, &opts.output, file_io::OutputFormat::Unknown)?;
+    age_threshold::decrypt_committee(&partials, &mut input, &mut output)
+}
+
+fn read_deals(paths: &[PathBuf]) -> io::Result<Vec<Deal>> {
+    let mut deals = vec![];
+    for path in paths {
+        deals.push(age_threshold::read_deal(&mut File::open(path)?)?);
+    }
+    Ok(deals)
+}
+
+fn first_line(lines: &[String], path: &Path) -> io::Result<String> {
+    lines
+        .iter()
+        .find(|l| !l.is_empty())
+        .cloned()
+        .ok_or_else(|| io::Error::other(format!("{} is empty", path.display())))
+}
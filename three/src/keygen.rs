@@ -0,0 +1,145 @@
+use age::armor::{ArmoredWriter, Format};
+use age::cli_common::{read_or_generate_passphrase, Passphrase};
+use age::secrecy::ExposeSecret;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use age_threshold::types::{AgeRecipient, RistrettoIdentity, ThresholdRecipient};
+
+use crate::{create_file, KeygenOpts};
+
+/// Generate a whole quorum: one identity file per holder plus a recipients file.
+pub(crate) fn keygen(opts: &KeygenOpts) -> io::Result<()> {
+    let n = opts.count;
+    if n == 0 {
+        return Err(io::Error::other("need at least one holder"));
+    }
+    let t = opts.threshold.unwrap_or(n / 2 + 1);
+    if t == 0 || t > n {
+        return Err(io::Error::other("invalid threshold"));
+    }
+    if opts.names.len() > n as usize {
+        return Err(io::Error::other("more names than holders"));
+    }
+    let dir = opts
+        .output_dir
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    let mut recipients = vec![];
+    let mut lines = vec![format!("# threshold: {}", t)];
+    for i in 1..=n {
+        let file_name = format!("key{}", i);
+        let path = dir.join(&file_name);
+        let (identity, recipient) = match &opts.plugin {
+            None if opts.ristretto => ristretto_keygen(),
+            None => native_keygen(),
+            Some(plugin) => plugin_keygen(plugin, &path)?,
+        };
+        let overwrite = opts.plugin.is_some();
+        if opts.passphrase {
+            write_encrypted(&path, &identity, &file_name, overwrite)?;
+        } else if !overwrite {
+            create_file(&path, false, 0o600)?.write_all(identity.as_bytes())?;
+        }
+
+        // Named holders get labeled in the header of files encrypted to this recipients file.
+        match opts.names.get(i as usize - 1) {
+            Some(name) => {
+                lines.push(format!("# {}", file_name));
+                lines.push(format!("{} = {}", name, recipient));
+            }
+            None => {
+                lines.push(format!("# holder {}: {}", i, file_name));
+                lines.push(recipient.clone());
+            }
+        }
+        recipients.push(AgeRecipient::from_bech32(&recipient).map_err(io::Error::other)?);
+    }
+
+    let mut f = create_file(&dir.join("recipients"), false, 0o644)?;
+    for l in &lines {
+        writeln!(f, "{}", l)?;
+    }
+
+    let quorum = ThresholdRecipient {
+        threshold: t,
+        recipients,
+    };
+    println!("{}", quorum.to_bech32());
+    Ok(())
+}
+
+fn native_keygen() -> (String, String) {
+    let identity = age::x25519::Identity::generate();
+    let recipient = identity.to_public().to_string();
+    (
+        format!(
+            "# public key: {}\n{}\n",
+            recipient,
+            identity.to_string().expose_secret()
+        ),
+        recipient,
+    )
+}
+
+fn ristretto_keygen() -> (String, String) {
+    let identity = RistrettoIdentity::generate();
+    let recipient = identity.to_public().to_string();
+    (
+        format!(
+            "# public key: {}\n{}\n",
+            recipient,
+            identity.to_bech32().expose_secret()
+        ),
+        recipient,
+    )
+}
+
+/// Let `age-plugin-NAME` generate an identity at `path`, then ask it for the recipient.
+fn plugin_keygen(plugin: &str, path: &Path) -> io::Result<(String, String)> {
+    let binary = format!("age-plugin-{}", plugin);
+    if path.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", path.display()),
+        ));
+    }
+    let status = Command::new(&binary).arg("-o").arg(path).status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("{} failed", binary)));
+    }
+    let output = Command::new(&binary).arg("-y").arg(path).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("{} failed", binary)));
+    }
+    let recipient = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .rfind(|l| !l.is_empty() && !l.starts_with('#'))
+        .ok_or(io::Error::other(format!("{} printed no recipient", binary)))?
+        .to_string();
+    Ok((std::fs::read_to_string(path)?, recipient))
+}
+
+fn write_encrypted(path: &Path, identity: &str, name: &str, overwrite: bool) -> io::Result<()> {
+    eprintln!("Choose a passphrase for {}.", name);
+    let passphrase =
+        match read_or_generate_passphrase().map_err(|e| io::Error::other(format!("{:?}", e)))? {
+            Passphrase::Typed(p) => p,
+            Passphrase::Generated(p) => {
+                eprintln!("Generated passphrase for {}: {}", name, p.expose_secret());
+                p
+            }
+        };
+    let output =
+        ArmoredWriter::wrap_output(create_file(path, overwrite, 0o600)?, Format::AsciiArmor)?;
+    let mut w = age::Encryptor::with_user_passphrase(passphrase)
+        .wrap_output(output)
+        .map_err(io::Error::other)?;
+    w.write_all(identity.as_bytes())?;
+    w.finish()?.finish()?;
+    Ok(())
+}
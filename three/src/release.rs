@@ -0,0 +1,344 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use age_threshold::types::{Request, ShareFile, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+use crate::{hex_fingerprint, parse_fingerprint};
+
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Shares about to leave their holder, as shown before releasing them and as logged.
+#[derive(Debug)]
+pub struct Release {
+    /// The subcommand releasing the shares.
+    pub action: &'static str,
+    pub fingerprint: [u8; 32],
+    pub threshold: u32,
+    pub indices: Vec<u32>,
+    pub label: String,
+    /// Whether the shares answer a request, rather than leave on the holder's own command.
+    pub requested: bool,
+    /// Verified signer of the request, if it is signed.
+    pub requester: Option<VerifyingKey>,
+    /// Verified signer of the header, if it is signed.
+    pub signer: Option<VerifyingKey>,
+}
+
+impl Release {
+    pub fn new(
+        action: &'static str,
+        share_files: &[ShareFile],
+        request: Option<&Request>,
+        signer: Option<VerifyingKey>,
+    ) -> Self {
+        Release {
+            action,
+            fingerprint: share_files[0].fingerprint,
+            threshold: share_files[0].threshold,
+            indices: share_files.iter().map(|f| f.share.index).collect(),
+            label: share_files[0].label.clone(),
+            requested: request.is_some(),
+            requester: request.and_then(|r| r.signer),
+            signer,
+        }
+    }
+
+    fn requester_name(&self) -> String {
+        match (self.requested, &self.requester) {
+            (_, Some(requester)) => requester.to_string(),
+            (true, None) => "unsigned request".to_string(),
+            (false, None) => "-".to_string(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        let mut s = format!(
+            "File fingerprint: {}\nThreshold: {}\nShare indices: {}\n",
+            hex_fingerprint(&self.fingerprint),
+            self.threshold,
+            join(&self.indices),
+        );
+        if !self.label.is_empty() {
+            s.push_str(&format!("Holder label: {}\n", self.label));
+        }
+        if self.requested {
+            s.push_str(&format!("Requester: {}\n", self.requester_name()));
+        }
+        match &self.signer {
+            Some(signer) => s.push_str(&format!("Signed by: {}\n", signer)),
+            None => s.push_str("Signed by: nobody\n"),
+        }
+        s
+    }
+}
+
+/// What a holder agrees to release without being asked.
+///
+/// Each line of a rules file is one of `allow-file FINGERPRINT`, `allow-requester KEY`
+/// or `rate-limit COUNT SECONDS`; anything after a `#` is a comment.
+/// A kind of rule that does not appear does not restrict anything,
+/// but rules that are all missing release nothing.
+#[derive(Debug, Default)]
+pub struct Rules {
+    /// Fingerprints of the headers to release shares for.
+    pub fingerprints: Vec<[u8; 32]>,
+    /// Long-term `ssh-ed25519` keys allowed to request shares, by signing their requests.
+    pub requesters: Vec<VerifyingKey>,
+    /// At most this many releases over this many seconds, as counted in the audit log.
+    pub rate_limit: Option<(usize, u64)>,
+}
+
+impl Rules {
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut rules = Rules::default();
+        for (n, line) in text.lines().enumerate() {
+            let invalid = || io::Error::other(format!("invalid rule on line {}", n + 1));
+            let line = line.split('#').next().unwrap();
+            let words: Vec<_> = line.split_whitespace().collect();
+            match words[..] {
+                [] => {}
+                ["allow-file", fingerprint] => {
+                    rules.fingerprints.push(parse_fingerprint(fingerprint)?)
+                }
+                ["allow-requester", kind, key, ..] => rules.requesters.push(
+                    VerifyingKey::from_openssh(&format!("{} {}", kind, key))
+                        .map_err(|_| invalid())?,
+                ),
+                ["rate-limit", count, seconds] => {
+                    rules.rate_limit = Some((
+                        count.parse().map_err(|_| invalid())?,
+                        seconds.parse().map_err(|_| invalid())?,
+                    ))
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(rules)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fingerprints.is_empty() && self.requesters.is_empty() && self.rate_limit.is_none()
+    }
+
+    fn check(&self, release: &Release, log: &AuditLog, now: u64) -> io::Result<()> {
+        // An empty or truncated rules file must not turn into releasing to anyone.
+        if self.is_empty() {
+            return Err(io::Error::other("no rule allows the release"));
+        }
+        if !self.fingerprints.is_empty() && !self.fingerprints.contains(&release.fingerprint) {
+            return Err(io::Error::other("file not allowed"));
+        }
+        if release.requested
+            && !self.requesters.is_empty()
+            && !release
+                .requester
+                .is_some_and(|r| self.requesters.contains(&r))
+        {
+            return Err(io::Error::other("requester not allowed"));
+        }
+        if let Some((count, seconds)) = self.rate_limit {
+            let recent = log
+                .read()?
+                .iter()
+                .filter(|e| e.time + seconds > now)
+                .count();
+            if recent >= count {
+                return Err(io::Error::other("rate limit exceeded"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Gate for every path where a share leaves its holder.
+///
+/// Without rules, the holder confirms each release on the terminal.
+/// Every release is appended to the audit log.
+pub struct Policy {
+    pub rules: Option<Rules>,
+    /// If not empty, only files signed by one of these keys are released, rules or not.
+    pub signers: Vec<VerifyingKey>,
+    pub log: AuditLog,
+}
+
+impl Policy {
+    pub fn new(
+        rules: &Option<PathBuf>,
+        audit_log: &Option<PathBuf>,
+        signers: Vec<VerifyingKey>,
+    ) -> io::Result<Self> {
+        let rules = match rules {
+            Some(path) => Some(Rules::parse(&std::fs::read_to_string(path)?)?),
+            None => None,
+        };
+        Ok(Policy {
+            rules,
+            signers,
+            log: AuditLog::open(audit_log)?,
+        })
+    }
+
+    /// Show `release`, check or confirm it, and log it.
+    ///
+    /// The new head of the audit log is printed, to be noted elsewhere: the hash chain
+    /// alone does not show that entries were removed from its end.
+    pub fn approve(&self, release: &Release) -> io::Result<()> {
+        eprint!("{}", release.describe());
+        if !self.signers.is_empty() && !release.signer.is_some_and(|s| self.signers.contains(&s)) {
+            return Err(io::Error::other("file not signed by a required signer"));
+        }
+        let now = now();
+        match &self.rules {
+            Some(rules) => rules.check(release, &self.log, now)?,
+            None => confirm()?,
+        }
+        let head = self.log.append(release, now)?;
+        eprintln!("Audit log head: {}", head);
+        Ok(())
+    }
+}
+
+fn confirm() -> io::Result<()> {
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map_err(|_| io::Error::other("no terminal to confirm the release on, use a rules file"))?;
+    write!(tty, "Release? [y/N] ")?;
+    let mut answer = String::new();
+    io::BufReader::new(tty).read_line(&mut answer)?;
+    match answer.trim() {
+        "y" | "Y" | "yes" => Ok(()),
+        _ => Err(io::Error::other("release declined")),
+    }
+}
+
+/// A local, append-only log of releases.
+///
+/// Each line holds the fields of a release, the hash of the previous line and its own
+/// hash, separated by tabs. Editing or removing a line breaks the chain after it.
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+pub struct Entry {
+    pub time: u64,
+    pub fields: Vec<String>,
+    pub hash: String,
+}
+
+impl AuditLog {
+    /// Open the log at `path`, or at `$THREE_AUDIT_LOG`, or in the XDG state directory.
+    pub fn open(path: &Option<PathBuf>) -> io::Result<Self> {
+        let path = match path {
+            Some(path) => path.clone(),
+            None => default_path()?,
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(AuditLog { path })
+    }
+
+    /// Read and verify the whole chain.
+    pub fn read(&self) -> io::Result<Vec<Entry>> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        let mut entries = vec![];
+        let mut prev = GENESIS.to_string();
+        for (n, line) in text.lines().enumerate() {
+            let broken = || {
+                io::Error::other(format!(
+                    "audit log {} broken at line {}",
+                    self.path.display(),
+                    n + 1
+                ))
+            };
+            let (body, hash) = line.rsplit_once('\t').ok_or_else(broken)?;
+            let mut fields: Vec<_> = body.split('\t').map(str::to_string).collect();
+            if fields.len() != 8 || fields.pop().unwrap() != prev || hash != entry_hash(body) {
+                return Err(broken());
+            }
+            entries.push(Entry {
+                time: fields[0].parse().map_err(|_| broken())?,
+                fields,
+                hash: hash.to_string(),
+            });
+            prev = hash.to_string();
+        }
+        Ok(entries)
+    }
+
+    /// Append `release`, returning the hash of its entry.
+    fn append(&self, release: &Release, time: u64) -> io::Result<String> {
+        let entries = self.read()?;
+        let prev = entries.last().map_or(GENESIS, |e| &e.hash);
+        let body = [
+            time.to_string(),
+            release.action.to_string(),
+            hex_fingerprint(&release.fingerprint),
+            release.threshold.to_string(),
+            join(&release.indices),
+            release.requester_name(),
+            clean(&release.label),
+            prev.to_string(),
+        ]
+        .join("\t");
+        let mut f: File = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let hash = entry_hash(&body);
+        writeln!(f, "{}\t{}", body, hash)?;
+        Ok(hash)
+    }
+}
+
+fn default_path() -> io::Result<PathBuf> {
+    if let Some(path) = std::env::var_os("THREE_AUDIT_LOG") {
+        return Ok(path.into());
+    }
+    let state = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => match std::env::var_os("HOME") {
+            Some(home) => Path::new(&home).join(".local/state"),
+            None => return Err(io::Error::other("no location for the audit log")),
+        },
+    };
+    Ok(state.join("three/audit.log"))
+}
+
+fn entry_hash(body: &str) -> String {
+    hex_fingerprint(&Sha256::digest(body.as_bytes()))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn join(indices: &[u32]) -> String {
+    indices
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Labels are free-form; keep them on one field.
+fn clean(label: &str) -> String {
+    let label = label.replace(['\t', '\n', '\r'], " ");
+    if label.is_empty() {
+        "-".to_string()
+    } else {
+        label
+    }
+}
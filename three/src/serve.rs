@@ -0,0 +1,259 @@
+use std::io;
+use std::io::prelude::*;
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use age_threshold::types::AgeIdentity;
+
+use crate::release::{Policy, Release};
+
+/// Requests are small: a header with a few stanzas per holder.
+const MAX_REQUEST_SIZE: u64 = 1 << 20;
+const ERROR_PREFIX: &[u8] = b"error: ";
+/// Connections are served one at a time, so a slow client must not hold the daemon:
+/// it has this long to send its request and read the response.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a holder daemon listens: `unix:PATH` or `tcp:HOST:PORT`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+impl FromStr for Endpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            Ok(Endpoint::Unix(path.into()))
+        } else if let Some(addr) = s.strip_prefix("tcp:") {
+            Ok(Endpoint::Tcp(addr.to_string()))
+        } else {
+            Err(format!(
+                "invalid endpoint {}, expected unix:PATH or tcp:HOST:PORT",
+                s
+            ))
+        }
+    }
+}
+
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Endpoint::Unix(path) => write!(f, "unix:{}", path.display()),
+            Endpoint::Tcp(addr) => write!(f, "tcp:{}", addr),
+        }
+    }
+}
+
+pub enum Listener {
+    Unix(UnixListener),
+    Tcp(TcpListener),
+}
+
+pub fn listen(endpoint: &Endpoint) -> io::Result<Listener> {
+    Ok(match endpoint {
+        Endpoint::Unix(path) => Listener::Unix(UnixListener::bind(path)?),
+        Endpoint::Tcp(addr) => Listener::Tcp(TcpListener::bind(addr)?),
+    })
+}
+
+/// Answer requests one connection at a time, forever.
+pub fn serve(
+    listener: Listener,
+    identities: &[AgeIdentity],
+    label: &str,
+    policy: &Policy,
+) -> io::Result<()> {
+    loop {
+        let result = match &listener {
+            Listener::Unix(l) => {
+                let (stream, _) = l.accept()?;
+                handle(
+                    &mut Deadline::new(stream, TIMEOUT),
+                    identities,
+                    label,
+                    policy,
+                )
+            }
+            Listener::Tcp(l) => {
+                let (stream, _) = l.accept()?;
+                handle(
+                    &mut Deadline::new(stream, TIMEOUT),
+                    identities,
+                    label,
+                    policy,
+                )
+            }
+        };
+        if let Err(err) = result {
+            eprintln!("Connection failed: {}", err);
+        }
+    }
+}
+
+/// Streams whose reads and writes can time out.
+trait Timeouts {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl Timeouts for UnixStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_write_timeout(self, timeout)
+    }
+}
+
+impl Timeouts for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_write_timeout(self, timeout)
+    }
+}
+
+/// A stream that fails every read and write once a deadline has passed.
+///
+/// A timeout per call would let a client that trickles bytes hold the connection forever,
+/// so each call only gets the time left until the deadline.
+struct Deadline<S> {
+    stream: S,
+    deadline: Instant,
+}
+
+impl<S: Timeouts> Deadline<S> {
+    fn new(stream: S, timeout: Duration) -> Self {
+        Deadline {
+            stream,
+            deadline: Instant::now() + timeout,
+        }
+    }
+
+    fn remaining(&self) -> io::Result<Duration> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "deadline passed"));
+        }
+        Ok(remaining)
+    }
+}
+
+impl<S: Timeouts + Read> Read for Deadline<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.set_read_timeout(Some(self.remaining()?))?;
+        self.stream.read(buf)
+    }
+}
+
+impl<S: Timeouts + Write> Write for Deadline<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.set_write_timeout(Some(self.remaining()?))?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+fn handle(
+    stream: &mut (impl Read + Write),
+    identities: &[AgeIdentity],
+    label: &str,
+    policy: &Policy,
+) -> io::Result<()> {
+    let mut request = vec![];
+    Read::by_ref(stream)
+        .take(MAX_REQUEST_SIZE)
+        .read_to_end(&mut request)?;
+
+    let mut response = vec![];
+    let result = age_threshold::read_request(&mut &request[..]).and_then(|request| {
+        let share_files = age_threshold::request_shares(identities, label, &request)?;
+        let signer = age_threshold::signer(&request.header)?;
+        policy.approve(&Release::new("serve", &share_files, Some(&request), signer))?;
+        age_threshold::respond_to(&request, &share_files, &mut response)?;
+        Ok(request.fingerprint())
+    });
+    match result {
+        Ok(fingerprint) => {
+            eprintln!(
+                "Released share for {}",
+                crate::hex_fingerprint(&fingerprint)
+            );
+            stream.write_all(&response)
+        }
+        Err(err) => {
+            eprintln!("Refused request: {}", err);
+            stream.write_all(ERROR_PREFIX)?;
+            writeln!(stream, "{}", err)
+        }
+    }
+}
+
+/// Send a request to the daemon at `endpoint` and return its response.
+pub fn ask(endpoint: &Endpoint, request: &[u8]) -> io::Result<Vec<u8>> {
+    let response = match endpoint {
+        Endpoint::Unix(path) => {
+            let mut stream = UnixStream::connect(path)?;
+            stream.write_all(request)?;
+            stream.shutdown(Shutdown::Write)?;
+            read_response(stream)?
+        }
+        Endpoint::Tcp(addr) => {
+            let mut stream = TcpStream::connect(addr)?;
+            stream.write_all(request)?;
+            stream.shutdown(Shutdown::Write)?;
+            read_response(stream)?
+        }
+    };
+    match response.strip_prefix(ERROR_PREFIX) {
+        Some(err) => Err(io::Error::other(
+            String::from_utf8_lossy(err).trim().to_string(),
+        )),
+        None => Ok(response),
+    }
+}
+
+fn read_response(mut stream: impl Read) -> io::Result<Vec<u8>> {
+    let mut response = vec![];
+    stream.read_to_end(&mut response)?;
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadline_stops_trickling_client() -> io::Result<()> {
+        let (server, mut client) = UnixStream::pair()?;
+        std::thread::spawn(move || {
+            while client.write_all(b"x").is_ok() {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        });
+        let start = Instant::now();
+        let mut buf = vec![];
+        let err = Deadline::new(server, Duration::from_millis(200))
+            .read_to_end(&mut buf)
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+        ));
+        assert!(!buf.is_empty());
+        assert!(start.elapsed() < Duration::from_secs(2));
+        Ok(())
+    }
+}